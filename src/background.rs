@@ -0,0 +1,50 @@
+use macroquad::prelude::*;
+
+const STARS: usize = 150;
+const STAR_LAYERS: usize = 3;
+const STAR_SPEED: f32 = 4.0;
+const GRADIENT_BANDS: usize = 16;
+const GRADIENT_PERIOD: f32 = 20.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BackgroundStyle {
+    Flat,
+    Stars,
+    Gradient,
+}
+
+pub fn draw_background(style: BackgroundStyle, color: Color, dimensions: Vec2, time: f64) {
+    let time = time as f32;
+    match style {
+        BackgroundStyle::Flat => {}
+        BackgroundStyle::Stars => {
+            let mut state: u32 = 0x2545_f491;
+            let mut next = || {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32
+            };
+            (0..STARS).for_each(|i| {
+                let layer = (i % STAR_LAYERS + 1) as f32;
+                let x = (next() * dimensions.x + time * STAR_SPEED * layer) % dimensions.x;
+                let y = next() * dimensions.y;
+                draw_circle(x, y, 0.5 * layer, Color::new(1.0, 1.0, 1.0, 0.05 * layer));
+            });
+        }
+        BackgroundStyle::Gradient => {
+            let height = dimensions.y / GRADIENT_BANDS as f32;
+            (0..GRADIENT_BANDS).for_each(|i| {
+                let phase = i as f32 / GRADIENT_BANDS as f32 + time / GRADIENT_PERIOD;
+                let glow = 0.02 + 0.02 * (phase * std::f32::consts::TAU).sin();
+                draw_rectangle(
+                    0.0,
+                    i as f32 * height,
+                    dimensions.x,
+                    height,
+                    Color::new(color.r + glow, color.g + glow, color.b + glow * 2.0, 1.0),
+                );
+            });
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Pause,
+    Peek,
+    SlowMotion,
+    Hint,
+    NudgeUp,
+    NudgeDown,
+    NudgeLeft,
+    NudgeRight,
+    Export,
+    Import,
+}
+
+pub const ACTIONS: [(&str, Action); 10] = [
+    ("Pause", Action::Pause),
+    ("Peek", Action::Peek),
+    ("Slow-mo", Action::SlowMotion),
+    ("Hint", Action::Hint),
+    ("Nudge Up", Action::NudgeUp),
+    ("Nudge Down", Action::NudgeDown),
+    ("Nudge Left", Action::NudgeLeft),
+    ("Nudge Right", Action::NudgeRight),
+    ("Export", Action::Export),
+    ("Import", Action::Import),
+];
+
+// Fixed keys outside the bindings, no action can take them over.
+pub const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+pub const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+
+// Keys that can be stored in the settings file, looked up by their debug name.
+const NAMED_KEYS: [KeyCode; 71] = [
+    KeyCode::Space,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+];
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    NAMED_KEYS
+        .into_iter()
+        .find(|key| format!("{:?}", key) == name)
+}
+
+#[derive(Clone)]
+pub struct KeyBindings {
+    keys: [KeyCode; ACTIONS.len()],
+}
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        Self {
+            keys: [
+                KeyCode::Escape,
+                KeyCode::P,
+                KeyCode::T,
+                KeyCode::H,
+                KeyCode::Up,
+                KeyCode::Down,
+                KeyCode::Left,
+                KeyCode::Right,
+                KeyCode::F9,
+                KeyCode::F10,
+            ],
+        }
+    }
+
+    pub fn from_keys(keys: [KeyCode; ACTIONS.len()]) -> Option<Self> {
+        let unique = keys
+            .iter()
+            .enumerate()
+            .all(|(i, key)| !keys[..i].contains(key));
+        unique.then_some(Self { keys })
+    }
+
+    pub fn keys(&self) -> &[KeyCode] {
+        &self.keys
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[action as usize]
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        is_key_pressed(self.key(action))
+    }
+
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> bool {
+        let taken = [FULLSCREEN_KEY, SCREENSHOT_KEY].contains(&key)
+            || ACTIONS
+                .iter()
+                .any(|&(_, other)| other != action && self.key(other) == key);
+        if !taken {
+            self.keys[action as usize] = key;
+        }
+        !taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_keys_cannot_be_bound() {
+        let mut bindings = KeyBindings::new();
+        for key in [FULLSCREEN_KEY, SCREENSHOT_KEY] {
+            assert!(!bindings.bind(Action::Pause, key));
+            assert_eq!(key_from_name(&format!("{:?}", key)), None);
+        }
+        assert_eq!(bindings.key(Action::Pause), KeyCode::Escape);
+        assert!(bindings.bind(Action::Pause, KeyCode::F8));
+    }
+}
@@ -0,0 +1,131 @@
+use macroquad::prelude::*;
+
+use crate::geometrie::{Aabb, Point};
+use crate::{CAMERA_SMOOTHING, GameSettings, MAX_ZOOM, MIN_ZOOM};
+
+pub struct Camera {
+    camera: Camera2D,
+    view: Rect,
+    focus: Vec2,
+    zoom: f32,
+}
+
+impl Camera {
+    pub fn new(settings: &GameSettings) -> Self {
+        let dimensions = settings.dimensions;
+        let view = Rect::new(0.0, 0.0, dimensions.x, dimensions.y);
+        Self {
+            camera: Camera2D::from_display_rect(view),
+            view,
+            focus: dimensions * 0.5,
+            zoom: 1.0,
+        }
+    }
+
+    pub fn update(&mut self, settings: &GameSettings, focus: Option<Point<f32>>) {
+        let dimensions = settings.dimensions;
+        let (zoom, focus) = match (settings.camera_follow, focus) {
+            (true, Some(focus)) => {
+                // Ease towards the player instead of locking onto every small movement.
+                let ease = 1.0 - (-CAMERA_SMOOTHING * get_frame_time()).exp();
+                (
+                    settings.zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+                    self.focus.lerp(vec2(focus.x, focus.y), ease),
+                )
+            }
+            _ => (1.0, dimensions * 0.5),
+        };
+        self.zoom = zoom;
+        self.focus = focus;
+        self.apply(settings);
+    }
+
+    // Eases out until all of `bounds` is in view, e.g. to show the solved maze.
+    pub fn fit(&mut self, settings: &GameSettings, bounds: &Aabb<f32>) {
+        let (zoom, center) = fit_view(settings.dimensions, bounds);
+        let ease = 1.0 - (-CAMERA_SMOOTHING * get_frame_time()).exp();
+        self.zoom += (zoom - self.zoom) * ease;
+        self.focus = self.focus.lerp(center, ease);
+        self.apply(settings);
+    }
+
+    fn apply(&mut self, settings: &GameSettings) {
+        let dimensions = settings.dimensions;
+        let size = dimensions / self.zoom;
+        self.focus = self.focus.clamp(size * 0.5, dimensions - size * 0.5);
+        let corner = self.focus - size * 0.5;
+        self.view = Rect::new(corner.x, corner.y, size.x, size.y);
+        self.camera = Camera2D::from_display_rect(self.view);
+        if settings.aspect_lock {
+            self.camera.viewport = Some(letterbox(dimensions));
+        }
+    }
+
+    pub fn enable(&self, background: Color) {
+        set_camera(&self.camera);
+        draw_rectangle(
+            self.view.x,
+            self.view.y,
+            self.view.w,
+            self.view.h,
+            background,
+        );
+    }
+
+    pub fn disable(&self) {
+        set_default_camera();
+    }
+
+    pub fn view(&self) -> Aabb<f32> {
+        Aabb::new(
+            Point::new(self.view.x, self.view.y),
+            Point::new(self.view.right(), self.view.bottom()),
+        )
+    }
+
+    pub fn to_world(&self, screen: Point<f32>) -> Point<f32> {
+        let world = self.camera.screen_to_world(vec2(screen.x, screen.y));
+        Point::new(world.x, world.y)
+    }
+}
+
+// The zoom and focus that show all of `bounds`, never zooming out past the whole window.
+fn fit_view(dimensions: Vec2, bounds: &Aabb<f32>) -> (f32, Vec2) {
+    let extent = vec2(bounds.max.x - bounds.min.x, bounds.max.y - bounds.min.y);
+    let zoom = (dimensions.x / extent.x)
+        .min(dimensions.y / extent.y)
+        .clamp(1.0, MAX_ZOOM);
+    let center = vec2(bounds.min.x + bounds.max.x, bounds.min.y + bounds.max.y) * 0.5;
+    (zoom, center)
+}
+
+fn letterbox(dimensions: Vec2) -> (i32, i32, i32, i32) {
+    let screen = vec2(screen_width(), screen_height());
+    let scale = (screen.x / dimensions.x).min(screen.y / dimensions.y);
+    let size = dimensions * scale;
+    let offset = (screen - size) * 0.5;
+    (
+        offset.x as i32,
+        offset.y as i32,
+        size.x as i32,
+        size.y as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_view_shows_the_whole_maze() {
+        let dimensions = vec2(1200.0, 800.0);
+        let maze = Aabb::new(Point::new(0.0, 0.0), Point::new(1200.0, 800.0));
+        assert_eq!(fit_view(dimensions, &maze), (1.0, vec2(600.0, 400.0)));
+        let half = Aabb::new(Point::new(100.0, 100.0), Point::new(700.0, 500.0));
+        assert_eq!(fit_view(dimensions, &half), (2.0, vec2(400.0, 300.0)));
+        let tall = Aabb::new(Point::new(0.0, 0.0), Point::new(100.0, 400.0));
+        assert_eq!(fit_view(dimensions, &tall).0, 2.0);
+        let tiny = Aabb::new(Point::new(10.0, 10.0), Point::new(20.0, 20.0));
+        assert_eq!(fit_view(dimensions, &tiny).0, MAX_ZOOM);
+    }
+}
@@ -0,0 +1,20 @@
+use macroquad::miniquad::window;
+
+pub trait Clipboard {
+    fn get(&mut self) -> Option<String>;
+    fn set(&mut self, text: &str) -> bool;
+}
+
+pub struct SystemClipboard;
+
+impl Clipboard for SystemClipboard {
+    fn get(&mut self) -> Option<String> {
+        window::clipboard_get()
+    }
+
+    fn set(&mut self, text: &str) -> bool {
+        window::clipboard_set(text);
+        // Read back, some platforms silently ignore clipboard writes.
+        self.get().as_deref() == Some(text)
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::bindings::{ACTIONS, KeyBindings, key_from_name};
+use crate::error::Error;
+use crate::theme::THEMES;
+use crate::{
+    ALGORITHMS, BACKGROUNDS, BOUNCES, CONE_HALF_ANGLE, DIFFICULTIES, DROPOUT, FPS_CAPS, GRID_SIZES,
+    GameSettings, LIGHT_RENDERS, MAX_CELL_ASPECT, MAX_GRID_SIZE, MAX_SEPARATION, MAX_VISION_RANGE,
+    MAX_ZOOM, MIN_CELL_ASPECT, MIN_CONE_ANGLE, MIN_GRID_SIZE, MIN_RADIUS, MIN_VISION_RANGE,
+    MIN_ZOOM, MOVEMENT_MODES, PLACEMENTS, PLAYER_RADIUS, RAY_COUNTS, TARGET_KEYS, TARGET_RADIUS,
+    VISION_MODES, VisionMode,
+};
+
+impl GameSettings {
+    pub fn load() -> Self {
+        let mut settings = GameSettings::new(false, true, 1, DROPOUT, PLAYER_RADIUS, TARGET_RADIUS);
+        if let Some(text) =
+            config_file("settings.toml").and_then(|path| fs::read_to_string(path).ok())
+        {
+            settings.apply_toml(&text);
+        }
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        match config_file("settings.toml") {
+            Some(path) => write_atomically(&path, &self.to_toml()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        let keys = self
+            .key_bindings
+            .keys()
+            .iter()
+            .map(|key| format!("\"{:?}\"", key))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let (vision_mode, cone_angle) = match self.vision_mode {
+            VisionMode::Omnidirectional => ("full", CONE_HALF_ANGLE),
+            VisionMode::Cone { half_angle } => ("cone", half_angle),
+        };
+        [
+            format!("difficulty = \"{}\"", name(&DIFFICULTIES, self.difficulty)),
+            format!("labyrinth_size = \"{}\"", GRID_SIZES[self.labyrinth_size].0),
+            format!("custom_grid_size = {}", self.custom_grid_size.unwrap_or(0)),
+            format!("algorithm = \"{}\"", name(&ALGORITHMS, self.algorithm)),
+            format!("dropout = {}", self.dropout),
+            format!("braid = {}", self.braid),
+            format!("cell_aspect = {}", self.cell_aspect),
+            format!("placement = \"{}\"", name(&PLACEMENTS, self.placement)),
+            format!("min_separation = {}", self.min_separation),
+            format!("theme = \"{}\"", THEMES[self.theme].0),
+            format!(
+                "light_render = \"{}\"",
+                name(&LIGHT_RENDERS, self.light_render)
+            ),
+            format!("ray_count = \"{}\"", name(&RAY_COUNTS, self.ray_count)),
+            format!("vision_range = {}", self.vision_range),
+            format!("vision_mode = \"{}\"", vision_mode),
+            format!("cone_angle = {}", cone_angle),
+            format!(
+                "movement_mode = \"{}\"",
+                name(&MOVEMENT_MODES, self.movement_mode)
+            ),
+            format!("background = \"{}\"", name(&BACKGROUNDS, self.background)),
+            format!("fps_cap = \"{}\"", name(&FPS_CAPS, self.fps_cap)),
+            format!("draw_labyrinth = {}", self.draw_labyrinth),
+            format!("practice = {}", self.practice),
+            format!("campaign = {}", self.campaign),
+            format!("countdown = {}", self.countdown),
+            format!("draw_delta_time = {}", self.draw_delta_time),
+            format!("draw_frame_stats = {}", self.draw_frame_stats),
+            format!("draw_grid_coords = {}", self.draw_grid_coords),
+            format!("keys_and_doors = {}", self.keys_and_doors),
+            format!("target_keys = \"{}\"", name(&TARGET_KEYS, self.target_keys)),
+            format!("bounces = \"{}\"", name(&BOUNCES, self.bounces)),
+            format!("wandering_target = {}", self.wandering_target),
+            format!("wall_memory = {}", self.wall_memory),
+            format!("dynamic_walls = {}", self.dynamic_walls),
+            format!("proximity_cue = {}", self.proximity_cue),
+            format!("trail = {}", self.trail),
+            format!("minimap = {}", self.minimap),
+            format!("camera_follow = {}", self.camera_follow),
+            format!("aspect_lock = {}", self.aspect_lock),
+            format!("fullscreen = {}", self.fullscreen),
+            format!("slow_motion = {}", self.slow_motion),
+            format!("player_radius = {}", self.player_radius),
+            format!("target_radius = {}", self.target_radius),
+            format!("wall_thickness = {}", self.wall_thickness),
+            format!("move_speed = {}", self.move_speed),
+            format!("mouse_sensitivity = {}", self.mouse_sensitivity),
+            format!("dead_zone = {}", self.dead_zone),
+            format!("stick_dead_zone = {}", self.stick_dead_zone),
+            format!("nudge_step = {}", self.nudge_step),
+            format!("win_animation = {}", self.win_animation),
+            format!("zoom = {}", self.zoom),
+            format!("time_scale = {}", self.time_scale),
+            format!("vignette = {}", self.vignette),
+            format!("key_bindings = [{}]", keys),
+        ]
+        .join("\n")
+            + "\n"
+    }
+
+    // Unknown keys and malformed values are skipped so the defaults stay in place.
+    pub fn apply_toml(&mut self, text: &str) {
+        let values: HashMap<&str, &str> = text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+
+        choice(&values, "difficulty", &DIFFICULTIES, &mut self.difficulty);
+        index(
+            &values,
+            "labyrinth_size",
+            &GRID_SIZES.map(|(name, _)| name),
+            &mut self.labyrinth_size,
+        );
+        let mut custom_grid_size = self.custom_grid_size.unwrap_or(0);
+        field(&values, "custom_grid_size", &mut custom_grid_size);
+        // Zero keeps the preset size.
+        self.custom_grid_size =
+            (custom_grid_size > 0).then(|| custom_grid_size.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE));
+        choice(&values, "algorithm", &ALGORITHMS, &mut self.algorithm);
+        bounded(&values, "dropout", 0.0..=1.0, &mut self.dropout);
+        bounded(&values, "braid", 0.0..=1.0, &mut self.braid);
+        bounded(
+            &values,
+            "cell_aspect",
+            MIN_CELL_ASPECT..=MAX_CELL_ASPECT,
+            &mut self.cell_aspect,
+        );
+        choice(&values, "placement", &PLACEMENTS, &mut self.placement);
+        bounded(
+            &values,
+            "min_separation",
+            0.0..=MAX_SEPARATION,
+            &mut self.min_separation,
+        );
+        index(
+            &values,
+            "theme",
+            &THEMES.map(|(name, _)| name),
+            &mut self.theme,
+        );
+        choice(
+            &values,
+            "light_render",
+            &LIGHT_RENDERS,
+            &mut self.light_render,
+        );
+        choice(&values, "ray_count", &RAY_COUNTS, &mut self.ray_count);
+        bounded(
+            &values,
+            "vision_range",
+            MIN_VISION_RANGE..=MAX_VISION_RANGE,
+            &mut self.vision_range,
+        );
+        let mut cone_angle = match self.vision_mode {
+            VisionMode::Cone { half_angle } => half_angle,
+            VisionMode::Omnidirectional => CONE_HALF_ANGLE,
+        };
+        number(&values, "cone_angle", &mut cone_angle);
+        choice(&values, "vision_mode", &VISION_MODES, &mut self.vision_mode);
+        if let VisionMode::Cone { half_angle } = &mut self.vision_mode {
+            *half_angle = cone_angle.clamp(MIN_CONE_ANGLE, 180.0);
+        }
+        choice(
+            &values,
+            "movement_mode",
+            &MOVEMENT_MODES,
+            &mut self.movement_mode,
+        );
+        choice(&values, "background", &BACKGROUNDS, &mut self.background);
+        choice(&values, "fps_cap", &FPS_CAPS, &mut self.fps_cap);
+        field(&values, "draw_labyrinth", &mut self.draw_labyrinth);
+        field(&values, "practice", &mut self.practice);
+        field(&values, "campaign", &mut self.campaign);
+        field(&values, "countdown", &mut self.countdown);
+        field(&values, "draw_delta_time", &mut self.draw_delta_time);
+        field(&values, "draw_frame_stats", &mut self.draw_frame_stats);
+        field(&values, "draw_grid_coords", &mut self.draw_grid_coords);
+        field(&values, "keys_and_doors", &mut self.keys_and_doors);
+        choice(&values, "target_keys", &TARGET_KEYS, &mut self.target_keys);
+        choice(&values, "bounces", &BOUNCES, &mut self.bounces);
+        field(&values, "wandering_target", &mut self.wandering_target);
+        field(&values, "wall_memory", &mut self.wall_memory);
+        field(&values, "dynamic_walls", &mut self.dynamic_walls);
+        field(&values, "proximity_cue", &mut self.proximity_cue);
+        field(&values, "trail", &mut self.trail);
+        field(&values, "minimap", &mut self.minimap);
+        field(&values, "camera_follow", &mut self.camera_follow);
+        field(&values, "aspect_lock", &mut self.aspect_lock);
+        field(&values, "fullscreen", &mut self.fullscreen);
+        field(&values, "slow_motion", &mut self.slow_motion);
+        bounded(
+            &values,
+            "player_radius",
+            MIN_RADIUS..=20.0,
+            &mut self.player_radius,
+        );
+        bounded(
+            &values,
+            "target_radius",
+            MIN_RADIUS..=30.0,
+            &mut self.target_radius,
+        );
+        bounded(
+            &values,
+            "wall_thickness",
+            1.0..=6.0,
+            &mut self.wall_thickness,
+        );
+        bounded(&values, "move_speed", 0.1..=3.0, &mut self.move_speed);
+        bounded(
+            &values,
+            "mouse_sensitivity",
+            0.1..=3.0,
+            &mut self.mouse_sensitivity,
+        );
+        bounded(&values, "dead_zone", 0.0..=20.0, &mut self.dead_zone);
+        bounded(
+            &values,
+            "stick_dead_zone",
+            0.0..=0.5,
+            &mut self.stick_dead_zone,
+        );
+        bounded(&values, "nudge_step", 0.5..=10.0, &mut self.nudge_step);
+        bounded(&values, "win_animation", 0.0..=3.0, &mut self.win_animation);
+        bounded(&values, "zoom", MIN_ZOOM..=MAX_ZOOM, &mut self.zoom);
+        bounded(&values, "time_scale", 0.1..=1.0, &mut self.time_scale);
+        bounded(&values, "vignette", 0.0..=1.0, &mut self.vignette);
+        self.apply_difficulty();
+
+        let keys = values.get("key_bindings").and_then(|value| {
+            let mut keys = value
+                .strip_prefix('[')?
+                .strip_suffix(']')?
+                .split(',')
+                .map(|key| key_from_name(unquote(key.trim())?))
+                .collect::<Option<Vec<_>>>()?;
+            // Files from before an action existed get its default key.
+            let defaults = KeyBindings::new();
+            keys.extend(defaults.keys().iter().skip(keys.len()));
+            KeyBindings::from_keys(<[_; ACTIONS.len()]>::try_from(keys).ok()?)
+        });
+        if let Some(keys) = keys {
+            self.key_bindings = keys;
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    let env = |name| std::env::var_os(name).map(PathBuf::from);
+    let dir = env("XDG_CONFIG_HOME")
+        .or_else(|| env("APPDATA"))
+        .or_else(|| env("HOME").map(|home| home.join(".config")))?;
+    Some(dir.join("dark_labyrinth").join(name))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn config_file(_name: &str) -> Option<PathBuf> {
+    None
+}
+
+pub fn write_atomically(path: &Path, contents: &str) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write next to the real file and rename over it, so a crash never leaves half a file.
+    let temporary = path.with_extension("tmp");
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+fn name<T: PartialEq>(options: &[(&'static str, T)], value: T) -> &'static str {
+    options
+        .iter()
+        .find(|(_, option)| *option == value)
+        .map_or(options[0].0, |(name, _)| name)
+}
+
+fn unquote(value: &str) -> Option<&str> {
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
+fn field<T: FromStr>(values: &HashMap<&str, &str>, key: &str, target: &mut T) {
+    if let Some(value) = values.get(key).and_then(|value| value.parse().ok()) {
+        *target = value;
+    }
+}
+
+fn number(values: &HashMap<&str, &str>, key: &str, target: &mut f32) {
+    if let Some(value) = values
+        .get(key)
+        .and_then(|value| value.parse::<f32>().ok())
+        .filter(|value| value.is_finite())
+    {
+        *target = value;
+    }
+}
+
+// Numbers the menu sets with a slider are held to the slider's range.
+fn bounded(values: &HashMap<&str, &str>, key: &str, range: RangeInclusive<f32>, target: &mut f32) {
+    number(values, key, target);
+    *target = target.clamp(*range.start(), *range.end());
+}
+
+fn choice<T: Copy>(values: &HashMap<&str, &str>, key: &str, options: &[(&str, T)], target: &mut T) {
+    if let Some((_, value)) = values
+        .get(key)
+        .and_then(|value| unquote(value))
+        .and_then(|value| options.iter().find(|(name, _)| *name == value))
+    {
+        *target = *value;
+    }
+}
+
+fn index(values: &HashMap<&str, &str>, key: &str, names: &[&str], target: &mut usize) {
+    if let Some(position) = values
+        .get(key)
+        .and_then(|value| unquote(value))
+        .and_then(|value| names.iter().position(|name| *name == value))
+    {
+        *target = position;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Difficulty;
+    use macroquad::prelude::KeyCode;
+
+    fn defaults() -> GameSettings {
+        GameSettings::new(false, true, 1, DROPOUT, PLAYER_RADIUS, TARGET_RADIUS)
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut settings = defaults();
+        settings.difficulty = Difficulty::Custom;
+        settings.custom_grid_size = Some(MIN_GRID_SIZE + 3);
+        settings.dropout = 0.25;
+        settings.cell_aspect = 1.5;
+        settings.vision_mode = VisionMode::Cone { half_angle: 60.0 };
+        settings.fps_cap = FPS_CAPS[2].1;
+        settings.trail = !settings.trail;
+        settings.nudge_step = 4.5;
+        settings
+            .key_bindings
+            .bind(crate::bindings::Action::Hint, KeyCode::J);
+        let text = settings.to_toml();
+        let mut loaded = defaults();
+        loaded.apply_toml(&text);
+        assert_eq!(loaded.to_toml(), text);
+    }
+
+    #[test]
+    fn broken_files_keep_the_defaults() {
+        let text = defaults().to_toml();
+        for broken in [
+            "",
+            "not toml at all\n= =\n===",
+            "zoom = \ndropout = abc\ntrail = maybe\nfps_cap = 1000\nkey_bindings = [\"Nope\"]",
+            "key_bindings = [\"Escape\", \"Escape\"]",
+            &text[..text.len() / 2],
+        ] {
+            let mut settings = defaults();
+            settings.apply_toml(broken);
+            assert_eq!(settings.to_toml(), text, "{}", broken);
+        }
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_clamped() {
+        let mut settings = defaults();
+        settings.apply_toml(
+            "difficulty = \"custom\"\ndropout = 7\nnudge_step = 99\nzoom = -5\n\
+             player_radius = 1e9\nstick_dead_zone = -1\ncell_aspect = 0\nmin_separation = 3",
+        );
+        assert_eq!(settings.dropout, 1.0);
+        assert_eq!(settings.nudge_step, 10.0);
+        assert_eq!(settings.zoom, MIN_ZOOM);
+        assert_eq!(settings.player_radius, 20.0);
+        assert_eq!(settings.stick_dead_zone, 0.0);
+        assert_eq!(settings.cell_aspect, MIN_CELL_ASPECT);
+        assert_eq!(settings.min_separation, MAX_SEPARATION);
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Asset(macroquad::Error),
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Maze(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Asset(e) => write!(f, "failed to load asset: {}", e),
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Image(e) => write!(f, "failed to write image: {}", e),
+            Error::Maze(reason) => write!(f, "invalid maze: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<macroquad::Error> for Error {
+    fn from(e: macroquad::Error) -> Self {
+        Error::Asset(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TimerError {
+    AlreadyStarted,
+    NotStarted,
+    NotRunning,
+    NotPaused,
+}
+
+impl fmt::Display for TimerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimerError::AlreadyStarted => write!(f, "the game timer is already started"),
+            TimerError::NotStarted => write!(f, "the game timer was never started"),
+            TimerError::NotRunning => write!(f, "the game timer is not running"),
+            TimerError::NotPaused => write!(f, "the game timer is not paused"),
+        }
+    }
+}
+
+impl std::error::Error for TimerError {}
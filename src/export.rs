@@ -0,0 +1,77 @@
+use macroquad::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::game::Game;
+use crate::{GameSettings, draw_labyrinth};
+
+const MAZE_FILE: &str = "maze.txt";
+
+pub fn export_png(game: &Game, settings: &GameSettings, markers: bool) -> Result<PathBuf, Error> {
+    let target = render_target(game.dimensions.x as u32, game.dimensions.y as u32);
+    let view = Rect::new(0.0, 0.0, game.dimensions.x, game.dimensions.y);
+    set_camera(&Camera2D {
+        render_target: Some(target.clone()),
+        ..Camera2D::from_display_rect(view)
+    });
+    let theme = settings.theme();
+    draw_rectangle(view.x, view.y, view.w, view.h, theme.background);
+    draw_labyrinth(game, settings, &game.bounds(), 1.0);
+    if markers {
+        draw_circle(game.spawn.x, game.spawn.y, game.player_radius, theme.player);
+        draw_circle(
+            game.target.x,
+            game.target.y,
+            game.target_radius,
+            theme.target,
+        );
+    }
+    set_default_camera();
+
+    let path = PathBuf::from(format!("maze-{}.png", game.seed));
+    save_png(&target.texture.get_texture_data(), &path)?;
+    Ok(path)
+}
+
+pub fn screenshot(seed: Option<u64>) -> Result<PathBuf, Error> {
+    let directory = PathBuf::from("screenshots");
+    fs::create_dir_all(&directory)?;
+    let timestamp = (miniquad::date::now() * 1000.0) as u64;
+    let name = match seed {
+        Some(seed) => format!("screenshot-{}-{}.png", timestamp, seed),
+        None => format!("screenshot-{}.png", timestamp),
+    };
+    let path = directory.join(name);
+    save_png(&get_screen_data(), &path)?;
+    Ok(path)
+}
+
+pub fn export_text(game: &Game) -> Result<PathBuf, Error> {
+    let path = PathBuf::from(MAZE_FILE);
+    fs::write(&path, game.to_ascii())?;
+    Ok(path)
+}
+
+pub fn import_text(settings: &GameSettings) -> Result<Game, Error> {
+    Game::from_ascii(settings, &fs::read_to_string(MAZE_FILE)?)
+}
+
+pub fn save_png(image: &Image, path: &Path) -> Result<(), Error> {
+    // GPU images come bottom row first.
+    let bytes = image
+        .bytes
+        .chunks(image.width as usize * 4)
+        .rev()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    image::save_buffer(
+        path,
+        &bytes,
+        image.width as u32,
+        image.height as u32,
+        image::ColorType::Rgba8,
+    )?;
+    Ok(())
+}
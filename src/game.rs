@@ -1,124 +1,1206 @@
 use itertools::Itertools;
 use macroquad::prelude::*;
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::ops::RangeInclusive;
 
-use crate::geometrie::{Line, Point};
-use crate::{RAY_LENGTH, RAYS, WINDOW_DIMENSIONS};
+use crate::bindings::Action;
+use crate::error::{Error, TimerError};
+use crate::geometrie::{Aabb, Circle, Line, Point};
+use crate::replay::Replay;
+use crate::rng::Rng;
+use crate::{
+    GAMEPAD_PULL, GATE_COUNT, GATE_PERIOD, GENERATION_ANIMATION, GENERATION_HOLD, GameSettings,
+    KEY_COLORS, KEYBOARD_PULL, MAX_VISION_RANGE, MIN_RADIUS, MIN_VISION_RANGE, MovementMode,
+    PLACEMENT_ATTEMPTS, TARGET_WANDER_PERIOD, TIME_BUDGETS, TRAIL_LENGTH, VisionMode,
+    WALL_MEMORY_BRIGHTNESS, WALL_MEMORY_FADE, WANDER_SEED,
+};
+
+pub struct Door {
+    pub edge: Line<f32>,
+    pub locked: bool,
+    pub color: Color,
+}
+
+pub struct Key {
+    pub position: Point<f32>,
+    pub color: Color,
+    pub collected: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Kruskal,
+    Dfs,
+    Prim,
+    Wilson,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Placement {
+    Random,
+    Farthest,
+}
+
+pub struct Gate {
+    pub edge: Line<f32>,
+    pub period: f32,
+    pub phase: f32,
+    pub closed: bool,
+}
+
+pub struct CellInfo {
+    pub position: Point<usize>,
+    pub center: Point<f32>,
+    pub walls: Vec<Line<f32>>,
+}
+
+pub struct MazeStats {
+    pub cells: usize,
+    pub walls: usize,
+    pub passages: usize,
+    pub dead_ends: usize,
+    pub diameter: usize,
+    pub solution_length: Option<usize>,
+}
+
+impl MazeStats {
+    fn new(grid: &Grid, walls: &[Line<f32>], spawn: &Point<f32>, target: &Point<f32>) -> Self {
+        let start = grid.find(spawn);
+        let goal = grid.find(target).position;
+        let farthest = |cell: &Cell| {
+            grid.distances(cell, &[])
+                .into_iter()
+                .max_by_key(|&(cell, distance)| (distance, cell))
+                .unwrap_or(((cell.position.x, cell.position.y), 0))
+        };
+        let (end, _) = farthest(start);
+        let (_, diameter) = farthest(&grid.cells[&end]);
+        Self {
+            cells: grid.cells.len(),
+            walls: walls.len(),
+            passages: grid.passages().len(),
+            dead_ends: grid
+                .cells
+                .values()
+                .filter(|cell| {
+                    DIRECTIONS
+                        .iter()
+                        .filter(|dir| grid.is_open(cell, dir, &[]))
+                        .filter(|dir| grid.neighbor(cell, dir).is_some())
+                        .count()
+                        == 1
+                })
+                .count(),
+            diameter,
+            solution_length: grid.distances(start, &[]).get(&(goal.x, goal.y)).copied(),
+        }
+    }
+}
 
 pub struct Game {
+    pub seed: u64,
     pub position: Point<f32>,
+    pub spawn: Point<f32>,
+    pub velocity: Point<f32>,
+    pub facing: Point<f32>,
     pub target: Point<f32>,
     pub timer: GameTimer,
+    pub replay: Replay,
     pub walls: Vec<Line<f32>>,
-    pub grid_size: usize,
+    pub doors: Vec<Door>,
+    pub keys: Vec<Key>,
+    pub target_keys: Vec<Point<f32>>,
+    pub gates: Vec<Gate>,
+    pub dimensions: Vec2,
+    pub cell_size: Point<usize>,
+    pub offset: Point<f32>,
+    pub player_radius: f32,
+    pub target_radius: f32,
+    pub wall_memory: HashMap<Line<usize>, f32>,
+    pub peek_timer: f32,
+    pub win_animation: f32,
+    pub floor: usize,
+    pub floors: usize,
+    pub stats: MazeStats,
+    pub hint: bool,
+    pub trail: VecDeque<Point<f32>>,
+    pub visited: HashSet<(usize, usize)>,
+    peek_cooldown: f32,
+    gate_time: f32,
     grid: Grid,
     base_rays: Vec<Point<f32>>,
-    threshold: f32,
+    rays: Vec<Point<f32>>,
+    bounces: Vec<Vec<Point<f32>>>,
+    solution: Vec<Point<f32>>,
+    solution_cell: Option<Point<usize>>,
+    trail_cell: Option<Point<usize>>,
+    step: Line<f32>,
+    target_key_spawns: Vec<Point<f32>>,
+    target_spawn: Point<f32>,
+    target_route: Vec<Point<usize>>,
+    wander_rng: Rng,
+    wandering: bool,
 }
 
 impl Game {
-    pub fn new(grid_size: usize, dropout: f32, target_threshold: usize) -> Self {
-        let walls = make_walls(grid_size, dropout);
-        let grid = Grid::new(grid_size).fill(&walls);
+    pub fn new(settings: &GameSettings) -> Self {
+        let seed = settings
+            .seed
+            .unwrap_or_else(|| (rand::rand() as u64) << 32 | rand::rand() as u64);
+        Self::with_seed(settings, seed)
+    }
+
+    pub fn with_seed(settings: &GameSettings, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let grid = Grid::new(settings.dimensions, settings.cell_size());
+        let walls = make_walls(
+            &mut rng,
+            &grid,
+            settings.algorithm,
+            settings.dropout,
+            settings.braid,
+        );
+        let grid = grid.fill(&walls);
+        let spawn = get_random_point(&mut rng, &grid);
+        let target = match settings.placement {
+            Placement::Random => {
+                get_separated_point(&mut rng, &grid, &spawn, settings.min_separation)
+            }
+            Placement::Farthest => get_farthest_point(&grid, &spawn),
+        };
+        Self::assemble(settings, seed, rng, walls, grid, spawn, target)
+    }
+
+    fn assemble(
+        settings: &GameSettings,
+        seed: u64,
+        mut rng: Rng,
+        walls: Vec<Line<f32>>,
+        grid: Grid,
+        spawn: Point<f32>,
+        target: Point<f32>,
+    ) -> Self {
+        let (cell_size, offset) = (grid.cell_size, grid.offset);
+        let (doors, keys) = match settings.keys_and_doors {
+            true => make_keys_and_doors(&mut rng, &grid, &spawn),
+            false => (Vec::new(), Vec::new()),
+        };
+        let stats = MazeStats::new(&grid, &walls, &spawn, &target);
+        let gates = match settings.dynamic_walls {
+            true => make_gates(&mut rng, &grid, &spawn, &target),
+            false => Vec::new(),
+        };
+        let target_keys = make_target_keys(&mut rng, &grid, &spawn, &target, settings.target_keys);
 
         Self {
-            position: get_random_point(grid_size),
-            target: get_random_point(grid_size),
-            timer: GameTimer::new(),
+            seed,
+            position: spawn,
+            spawn,
+            velocity: Point::new(0.0, 0.0),
+            facing: Point::new(0.0, -1.0),
+            target,
+            timer: GameTimer {
+                practice: settings.practice,
+                mode: match settings.countdown {
+                    true => TimerMode::CountDown {
+                        budget: TIME_BUDGETS[settings.labyrinth_size],
+                    },
+                    false => TimerMode::CountUp,
+                },
+                ..GameTimer::new()
+            },
+            replay: Replay::new(seed),
             walls,
-            grid_size,
+            doors,
+            keys,
+            target_keys: target_keys.clone(),
+            gates,
+            dimensions: settings.dimensions,
+            cell_size,
+            offset,
+            player_radius: settings.player_radius.max(MIN_RADIUS),
+            target_radius: settings.target_radius.max(MIN_RADIUS),
+            wall_memory: HashMap::new(),
+            peek_timer: 0.0,
+            win_animation: 0.0,
+            floor: 1,
+            stats,
+            floors: match settings.campaign {
+                true => settings.floors.max(1),
+                false => 1,
+            },
+            hint: false,
+            trail: VecDeque::with_capacity(TRAIL_LENGTH),
+            visited: HashSet::new(),
+            peek_cooldown: 0.0,
+            gate_time: 0.0,
+            base_rays: get_ray_directions(
+                settings.ray_count,
+                ray_length(cell_size, settings.vision_range),
+            ),
+            rays: Vec::new(),
+            bounces: Vec::new(),
+            solution: Vec::new(),
+            solution_cell: None,
+            trail_cell: None,
+            step: Line::new(spawn, spawn),
+            target_key_spawns: target_keys,
+            target_spawn: target,
+            target_route: vec![grid.find(&target).position],
+            wander_rng: Rng::new(seed ^ WANDER_SEED),
+            wandering: settings.wandering_target,
             grid,
-            base_rays: get_ray_directions(RAYS, (grid_size * RAY_LENGTH) as f32),
-            threshold: (grid_size / target_threshold) as f32,
         }
     }
 
-    pub fn update_position(&mut self) {
-        let mouse_position = Point::from(mouse_position());
-        let new_position = self.position + (mouse_position - self.position) * 0.1;
+    pub fn restart(&mut self, settings: &GameSettings) {
+        if self.floor > 1 {
+            *self = Self::with_seed(settings, self.seed);
+            if let Err(e) = self.timer.start() {
+                error!("{}", e);
+            }
+            return;
+        }
+        self.rewind();
+        self.replay.clear();
+        self.base_rays = get_ray_directions(
+            settings.ray_count,
+            ray_length(self.cell_size, settings.vision_range),
+        );
+        self.timer.reset();
+        if let Err(e) = self.timer.start() {
+            error!("{}", e);
+        }
+    }
+
+    // Puts the maze back to its starting state, but keeps the timer and the recorded run.
+    pub fn rewind(&mut self) {
+        self.position = self.spawn;
+        self.velocity = Point::new(0.0, 0.0);
+        self.facing = Point::new(0.0, -1.0);
+        self.keys.iter_mut().for_each(|key| key.collected = false);
+        self.doors.iter_mut().for_each(|door| door.locked = true);
+        self.target_keys = self.target_key_spawns.clone();
+        self.target = self.target_spawn;
+        self.target_route.truncate(1);
+        self.wander_rng = Rng::new(self.seed ^ WANDER_SEED);
+        self.wall_memory.clear();
+        self.peek_timer = 0.0;
+        self.win_animation = 0.0;
+        self.peek_cooldown = 0.0;
+        self.gate_time = 0.0;
+        self.solution_cell = None;
+        self.trail.clear();
+        self.trail_cell = None;
+        self.visited.clear();
+        self.step = Line::new(self.spawn, self.spawn);
+    }
+
+    // Moves the player along a recorded run instead of following live input.
+    pub fn play_back(&mut self, settings: &GameSettings, time: f64, position: Point<f32>) {
+        if position != self.position {
+            self.facing = (position - self.position).normalized();
+        }
+        self.step = Line::new(self.position, position);
+        self.position = position;
+        self.gate_time = time as f32;
+        self.update_gates();
+        self.target = self.target_at(time);
+        self.collect_keys();
+        self.cast_rays(settings);
+        self.update_trail();
+    }
+
+    pub fn is_last_floor(&self) -> bool {
+        self.floor >= self.floors
+    }
+
+    pub fn descend(&mut self, settings: &GameSettings) {
+        let floor = self.floor + 1;
+        let seed = self.seed ^ (floor as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut next = Self::with_seed(&settings.for_floor(floor), seed);
+        next.seed = self.seed;
+        next.floor = floor;
+        next.hint = self.hint;
+        next.timer = std::mem::replace(&mut self.timer, GameTimer::new());
+        next.timer.lap();
+        *self = next;
+    }
+
+    pub fn peek(&mut self, settings: &GameSettings) {
+        if self.peek_cooldown > 0.0 {
+            return;
+        }
+        self.peek_timer = settings.peek_duration;
+        self.peek_cooldown = settings.peek_cooldown;
+        self.timer.add_penalty(settings.peek_penalty as f64);
+    }
+
+    pub fn update_position(
+        &mut self,
+        settings: &GameSettings,
+        mouse: Point<f32>,
+        stick: Option<Point<f32>>,
+    ) {
+        let dt = get_frame_time();
+        let previous = self.position;
+        self.peek_timer = (self.peek_timer - dt).max(0.0);
+        self.win_animation = (self.win_animation - dt).max(0.0);
+        self.peek_cooldown = (self.peek_cooldown - dt).max(0.0);
+        let dt = match settings.slow_motion {
+            true => dt * settings.time_scale,
+            false => dt,
+        };
+        if self.timer.is_running() {
+            self.gate_time += dt;
+        }
+        self.update_gates();
+        if self.wandering {
+            self.target = self.target_at(self.timer.elapsed());
+        }
+        let delta = match (stick, settings.movement_mode) {
+            (Some(stick), _) if stick.norm() < settings.stick_dead_zone => Point::new(0.0, 0.0),
+            (Some(stick), _) => stick * GAMEPAD_PULL,
+            (None, MovementMode::Mouse) => match mouse - self.position {
+                d if d.norm() < settings.dead_zone => Point::new(0.0, 0.0),
+                d => d * settings.mouse_sensitivity,
+            },
+            (None, MovementMode::Keyboard) => keyboard_direction() * KEYBOARD_PULL,
+        };
+
+        self.velocity += delta * settings.acceleration * dt;
+        self.velocity *= (-settings.friction * dt).exp();
+        let speed = self.velocity.norm();
+        if speed > settings.max_speed {
+            self.velocity *= settings.max_speed / speed;
+        }
+        if speed > 1.0 {
+            self.facing = self.velocity.normalized();
+        }
+
+        // Never move further than one cell per frame, so walls can't be skipped.
+        let mut step = self.velocity * dt * settings.move_speed;
+        let max_step = self.cell_size.x.min(self.cell_size.y) as f32;
+        if step.norm() > max_step {
+            step = step.normalized() * max_step;
+        }
+        let new_position = self.position + step;
         let direction = Line::new(self.position, new_position);
         let cell = self.grid.find(&self.position);
 
-        match self
-            .grid
-            .find_intersection(&direction, cell, Direction::None)
-        {
-            Some(p) => {
+        match self.cast(&direction, cell) {
+            Some((p, wall)) => {
                 let direction = p - self.position;
                 let distance = direction.norm();
-                self.position = self.position + direction * (distance - 1.0) / distance;
+                self.position += direction * (distance - 1.0) / distance;
+
+                let along = (wall.b - wall.a).normalized();
+                self.velocity = along * self.velocity.dot(along);
+                // Slide the blocked rest of the step along the wall, stopping at the next one.
+                let rest = new_position - self.position;
+                self.nudge(along * rest.dot(along));
             }
             _ => self.position = new_position,
         }
+
+        let nudge = [
+            (Action::NudgeUp, Point::new(0.0, -1.0)),
+            (Action::NudgeDown, Point::new(0.0, 1.0)),
+            (Action::NudgeLeft, Point::new(-1.0, 0.0)),
+            (Action::NudgeRight, Point::new(1.0, 0.0)),
+        ]
+        .into_iter()
+        .filter(|(action, _)| settings.key_bindings.pressed(*action))
+        .fold(Point::new(0.0, 0.0), |sum, (_, step)| sum + step);
+        if settings.movement_mode == MovementMode::Mouse && nudge.snorm() > 0.0 {
+            self.nudge(nudge * settings.nudge_step);
+        }
+
+        self.step = Line::new(previous, self.position);
+        self.collect_keys();
+        self.cast_rays(settings);
+        self.update_solution();
+        self.update_trail();
+
+        if self.timer.is_running() {
+            self.replay.record(self.timer.elapsed(), self.position);
+        }
     }
 
-    pub fn get_rays(&self) -> Vec<Point<f32>> {
-        let cell = self.grid.find(&self.position);
+    fn nudge(&mut self, offset: Point<f32>) {
+        let new_position = self.position + offset;
+        let direction = Line::new(self.position, new_position);
+        match self.cast(&direction, self.grid.find(&self.position)) {
+            Some((p, _)) => {
+                let direction = p - self.position;
+                let distance = direction.norm();
+                if distance > 1.0 {
+                    self.position += direction * (distance - 1.0) / distance;
+                }
+            }
+            _ => self.position = new_position,
+        }
+    }
+
+    // The target walks one cell per period along a route that only depends on the seed,
+    // so its position is a function of the elapsed time and replays can be verified.
+    pub fn target_at(&mut self, time: f64) -> Point<f32> {
+        if !self.wandering {
+            return self.target;
+        }
+        let step = (time.max(0.0) / TARGET_WANDER_PERIOD) as usize;
+        while self.target_route.len() < step + 2 {
+            let current = self.target_route[self.target_route.len() - 1];
+            let previous = self
+                .target_route
+                .len()
+                .checked_sub(2)
+                .map(|i| self.target_route[i]);
+            let mut options = self.grid.open_neighbors(current);
+            // Only turn back at dead ends, so the target keeps roaming.
+            if options.len() > 1 {
+                options.retain(|option| Some(*option) != previous);
+            }
+            let next = match options.is_empty() {
+                true => current,
+                false => options[self.wander_rng.index(options.len())],
+            };
+            self.target_route.push(next);
+            self.solution_cell = None;
+        }
+        let center = |cell: Point<usize>| cell_center(cell.x, cell.y, self.cell_size, self.offset);
+        let (from, to) = (
+            center(self.target_route[step]),
+            center(self.target_route[step + 1]),
+        );
+        let t = (time.max(0.0) / TARGET_WANDER_PERIOD).fract() as f32;
+        from.lerp(&to, t)
+    }
+
+    pub fn solve(&self) -> Vec<Point<f32>> {
+        let start = self.grid.find(&self.position);
+        let goal = self.grid.find(&self.target);
+        match self.grid.solve(start, goal) {
+            Some(cells) => cells
+                .iter()
+                .map(|cell| cell_center(cell.x, cell.y, self.cell_size, self.offset))
+                .chain([self.target])
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn solution(&self) -> &[Point<f32>] {
+        &self.solution
+    }
+
+    fn update_solution(&mut self) {
+        if !self.hint {
+            return;
+        }
+        let cell = self.grid.find(&self.position).position;
+        if self.solution_cell != Some(cell) {
+            self.solution = self.solve();
+            self.solution_cell = Some(cell);
+        }
+    }
+
+    fn update_trail(&mut self) {
+        let cell = self.grid.find(&self.position).position;
+        self.visited.insert((cell.x, cell.y));
+        if self.trail_cell == Some(cell) {
+            return;
+        }
+        if self.trail.len() >= TRAIL_LENGTH {
+            self.trail.pop_front();
+        }
+        self.trail.push_back(self.position);
+        self.trail_cell = Some(cell);
+    }
+
+    fn collect_keys(&mut self) {
+        let reach = self.player_radius + self.target_radius;
+        for key in self.keys.iter_mut().filter(|key| !key.collected) {
+            if (key.position - self.position).snorm() < reach * reach {
+                key.collected = true;
+                self.doors
+                    .iter_mut()
+                    .filter(|door| door.color == key.color)
+                    .for_each(|door| door.locked = false);
+                self.timer.lap();
+            }
+        }
+        let position = self.position;
+        let remaining = self.target_keys.len();
+        self.target_keys
+            .retain(|key| (*key - position).snorm() >= reach * reach);
+        for _ in self.target_keys.len()..remaining {
+            self.timer.lap();
+        }
+    }
 
-        self.base_rays
+    fn update_gates(&mut self) {
+        let time = self.gate_time;
+        self.gates
+            .iter_mut()
+            .for_each(|gate| gate.closed = ((time + gate.phase) / gate.period).fract() < 0.5);
+    }
+
+    fn barriers(&self) -> impl Iterator<Item = &Line<f32>> {
+        let doors = self
+            .doors
             .iter()
-            .map(|&r| {
+            .filter(|door| door.locked)
+            .map(|door| &door.edge);
+        let gates = self
+            .gates
+            .iter()
+            .filter(|gate| gate.closed)
+            .map(|gate| &gate.edge);
+        doors.chain(gates)
+    }
+
+    fn cast(&self, line: &Line<f32>, cell: &Cell) -> Option<(Point<f32>, Line<f32>)> {
+        self.nearest(
+            line,
+            self.grid.find_intersection(line, cell, Direction::None),
+        )
+    }
+
+    fn nearest(
+        &self,
+        line: &Line<f32>,
+        hit: Option<(Point<f32>, Line<f32>)>,
+    ) -> Option<(Point<f32>, Line<f32>)> {
+        self.barriers()
+            .filter_map(|edge| line.intersection(edge).map(|p| (p, *edge)))
+            .chain(hit)
+            .min_by(|(p1, _), (p2, _)| line.a.distance(p1).total_cmp(&line.a.distance(p2)))
+    }
+
+    fn ray_directions(&self, vision_mode: VisionMode) -> Vec<Point<f32>> {
+        let VisionMode::Cone { half_angle } = vision_mode else {
+            return self.base_rays.clone();
+        };
+        let limit = half_angle.to_radians().cos();
+        let within = |r: &Point<f32>| r.dot(self.facing) >= limit * r.norm();
+        // Start right after the dark side, so the cone comes out as one sweep.
+        let count = self.base_rays.len();
+        let start = (0..count)
+            .find(|&i| {
+                within(&self.base_rays[i]) && !within(&self.base_rays[(i + count - 1) % count])
+            })
+            .unwrap_or(0);
+        (0..count)
+            .map(|i| self.base_rays[(start + i) % count])
+            .filter(within)
+            .collect()
+    }
+
+    fn cast_rays(&mut self, settings: &GameSettings) {
+        let directions = self.ray_directions(settings.vision_mode);
+        let hits = self
+            .grid
+            .cast_rays(self.position, &directions)
+            .into_iter()
+            .zip(&directions)
+            .map(|(hit, &r)| {
                 let p2 = self.position + r;
                 let ray = Line::new(self.position, p2);
-                match self.grid.find_intersection(&ray, cell, Direction::None) {
-                    Some(p) => p,
-                    _ => p2,
+                match self.nearest(&ray, hit) {
+                    Some((p, wall)) => (p, Some(wall)),
+                    _ => (p2, None),
                 }
             })
+            .collect::<Vec<_>>();
+
+        self.bounces = match settings.bounces {
+            0 => Vec::new(),
+            bounces => hits
+                .iter()
+                .zip(&directions)
+                .map(|(&hit, &r)| self.bounce(hit, r, bounces))
+                .collect(),
+        };
+
+        if settings.wall_memory {
+            let fade = get_frame_time() * WALL_MEMORY_FADE;
+            self.wall_memory
+                .values_mut()
+                .for_each(|b| *b = (*b - fade).max(WALL_MEMORY_BRIGHTNESS));
+            hits.iter()
+                .filter_map(|(_, wall)| wall.as_ref())
+                .filter(|wall| {
+                    !self.doors.iter().any(|door| door.edge.contains(wall))
+                        && !self.gates.iter().any(|gate| gate.edge.contains(wall))
+                })
+                .for_each(|wall| {
+                    self.wall_memory
+                        .insert(edge_key(wall, self.cell_size, self.offset), 1.0);
+                });
+        }
+
+        self.rays = hits.into_iter().map(|(p, _)| p).collect();
+    }
+
+    pub fn is_legal_move(&self, from: Point<f32>, to: Point<f32>) -> bool {
+        self.grid.contains(&from)
+            && self.grid.contains(&to)
+            && self
+                .cast(&Line::new(from, to), self.grid.find(&from))
+                .is_none()
+    }
+
+    // Takes one recorded step, with the doors and gates as they were at its time.
+    pub fn replay_move(&mut self, time: f64, to: Point<f32>) -> bool {
+        self.gate_time = time as f32;
+        self.update_gates();
+        if !self.is_legal_move(self.position, to) {
+            return false;
+        }
+        self.position = to;
+        self.collect_keys();
+        true
+    }
+
+    // Follows a ray off the walls it hits until its length runs out, one point per bounce.
+    fn bounce(
+        &self,
+        mut hit: (Point<f32>, Option<Line<f32>>),
+        ray: Point<f32>,
+        bounces: usize,
+    ) -> Vec<Point<f32>> {
+        let mut path = Vec::new();
+        let (mut from, mut direction) = (self.position, ray);
+        let mut remaining = ray.norm();
+        for _ in 0..bounces {
+            // A corner has no single mirror axis, so light hitting one is not reflected.
+            let (p, Some(wall)) = hit else {
+                break;
+            };
+            if p.distance(&wall.a) < 1.0 || p.distance(&wall.b) < 1.0 {
+                break;
+            }
+            let travelled = from.distance(&p);
+            remaining -= travelled;
+            if remaining <= 0.0 {
+                break;
+            }
+            // Back off the wall a little, so the reflection starts in the cell the ray came from.
+            let incoming = direction.normalized();
+            from = p - incoming * (travelled * 0.5).min(0.5);
+            direction = wall.reflect(incoming) * remaining;
+            let line = Line::new(from, from + direction);
+            hit = match self.cast(&line, self.grid.find(&from)) {
+                Some((p, wall)) => (p, Some(wall)),
+                _ => (line.b, None),
+            };
+            path.push(hit.0);
+        }
+        path
+    }
+
+    pub fn nearest_wall(&self) -> Option<f32> {
+        self.walls
+            .iter()
+            .map(|wall| wall.distance_to_point(&self.position))
+            .min_by(f32::total_cmp)
+    }
+
+    // Extent of the maze, taken from the walls that enclose it.
+    pub fn bounds(&self) -> Aabb<f32> {
+        Aabb::from_points(
+            &self
+                .walls
+                .iter()
+                .flat_map(|wall| [wall.a, wall.b])
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    pub fn get_rays(&self) -> &[Point<f32>] {
+        &self.rays
+    }
+
+    // Each ray as a polyline from the player, through its bounces when mirrors are on.
+    pub fn get_ray_paths(&self) -> impl Iterator<Item = Vec<Point<f32>>> + '_ {
+        self.rays.iter().enumerate().map(|(i, &ray)| {
+            [self.position, ray]
+                .into_iter()
+                .chain(self.bounces.get(i).into_iter().flatten().copied())
+                .collect()
+        })
+    }
+
+    pub fn nearby_cells(&self) -> Vec<CellInfo> {
+        let cell = self.grid.find(&self.position);
+        [cell]
+            .into_iter()
+            .chain(
+                DIRECTIONS
+                    .iter()
+                    .filter_map(|dir| self.grid.neighbor(cell, dir)),
+            )
+            .map(|cell| self.cell_info(cell))
+            .collect()
+    }
+
+    pub fn visited_cells(&self) -> Vec<CellInfo> {
+        self.visited
+            .iter()
+            .filter_map(|position| self.grid.cells.get(position))
+            .map(|cell| self.cell_info(cell))
             .collect()
     }
 
+    fn cell_info(&self, cell: &Cell) -> CellInfo {
+        let walls = DIRECTIONS
+            .iter()
+            .filter(|dir| cell.walls.contains_key(dir))
+            .map(|dir| cell.borders[dir])
+            .collect();
+        CellInfo {
+            position: cell.position,
+            center: cell_center(
+                cell.position.x,
+                cell.position.y,
+                self.cell_size,
+                self.offset,
+            ),
+            walls,
+        }
+    }
+
+    pub fn target_discovered(&self) -> bool {
+        let cell = self.grid.find(&self.target).position;
+        self.visited.contains(&(cell.x, cell.y))
+    }
+
+    // Checked along the whole last step, so a fast player can't pass through the target.
     pub fn found_target(&self) -> bool {
-        self.position.distance(&self.target) < self.threshold
+        let reach = self.player_radius + self.target_radius;
+        self.target_keys.is_empty() && Circle::new(self.target, reach).intersects_line(&self.step)
     }
+
+    // One character per cell, wall and post: '#' is a wall, 'S' the spawn and 'T' the target.
+    pub fn to_ascii(&self) -> String {
+        let Point {
+            x: columns,
+            y: rows,
+        } = self.grid.size;
+        let mut chars = vec![vec!['#'; 2 * columns + 1]; 2 * rows + 1];
+        let (spawn, target) = (self.grid.find(&self.spawn), self.grid.find(&self.target));
+        for cell in self.grid.cells.values() {
+            let (x, y) = (2 * cell.position.x + 1, 2 * cell.position.y + 1);
+            chars[y][x] = match cell.position {
+                // Both in one cell, which only the import can tell apart from a missing target.
+                p if p == spawn.position && p == target.position => 'X',
+                p if p == spawn.position => 'S',
+                p if p == target.position => 'T',
+                _ => ' ',
+            };
+            for (dir, (x, y)) in [
+                (Direction::North, (x, y - 1)),
+                (Direction::East, (x + 1, y)),
+                (Direction::South, (x, y + 1)),
+                (Direction::West, (x - 1, y)),
+            ] {
+                if !cell.walls.contains_key(&dir) {
+                    chars[y][x] = ' ';
+                }
+            }
+        }
+        chars
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect()
+    }
+
+    pub fn from_ascii(settings: &GameSettings, text: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::Maze(reason.to_owned());
+        let chars = text
+            .lines()
+            .map(|line| line.trim_end_matches('\r').chars().collect::<Vec<_>>())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let (height, width) = (chars.len(), chars.first().map_or(0, |row| row.len()));
+        if height < 3 || width < 3 || height % 2 == 0 || width % 2 == 0 {
+            return Err(invalid("the grid needs an odd number of rows and columns"));
+        }
+        if chars.iter().any(|row| row.len() != width) {
+            return Err(invalid("all rows must have the same length"));
+        }
+        let (columns, rows) = ((width - 1) / 2, (height - 1) / 2);
+        let dimensions = settings.dimensions;
+        let cell_size = Point::new(
+            dimensions.x as usize / columns,
+            dimensions.y as usize / rows,
+        );
+        if cell_size.x == 0
+            || cell_size.y == 0
+            || grid_size(dimensions, cell_size) != Point::new(columns, rows)
+        {
+            return Err(invalid("the grid does not fit the window"));
+        }
+
+        let offset = grid_offset(dimensions, cell_size);
+        let mut edges = Vec::new();
+        let (mut spawn, mut target) = (Vec::new(), Vec::new());
+        for (y, row) in chars.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                let (i, j) = (x / 2, y / 2);
+                match (x % 2, y % 2, c) {
+                    (0, 0, _) => {}
+                    (1, 1, ' ') => {}
+                    (1, 1, 'S') => spawn.push(cell_center(i, j, cell_size, offset)),
+                    (1, 1, 'T') => target.push(cell_center(i, j, cell_size, offset)),
+                    (1, 1, 'X') => {
+                        spawn.push(cell_center(i, j, cell_size, offset));
+                        target.push(cell_center(i, j, cell_size, offset));
+                    }
+                    (_, _, ' ') if x == 0 || y == 0 || x == width - 1 || y == height - 1 => {
+                        return Err(invalid("the outer wall must be closed"));
+                    }
+                    (_, _, ' ') => {}
+                    (1, 0, '#') => edges.push(Line::new(Point::new(i, j), Point::new(i + 1, j))),
+                    (0, 1, '#') => edges.push(Line::new(Point::new(i, j), Point::new(i, j + 1))),
+                    _ => {
+                        return Err(invalid(&format!(
+                            "unexpected '{}' at {}:{}",
+                            c,
+                            y + 1,
+                            x + 1
+                        )));
+                    }
+                }
+            }
+        }
+        let ([spawn], [target]) = (spawn.as_slice(), target.as_slice()) else {
+            return Err(invalid("expected exactly one 'S' and one 'T'"));
+        };
+
+        let walls = compress_labyrinth(edges)
+            .into_iter()
+            .map(|line| Line::<f32>::from(line.scale(cell_size)).translate(offset))
+            .collect::<Vec<_>>();
+        let grid = Grid::new(dimensions, cell_size).fill(&walls);
+        // Imported mazes can't be regenerated from a seed, so they never count for scores.
+        let seed = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        let mut game = Self::assemble(settings, seed, Rng::new(seed), walls, grid, *spawn, *target);
+        game.timer.practice = true;
+        game.floors = 1;
+        Ok(game)
+    }
+}
+
+fn keyboard_direction() -> Point<f32> {
+    let direction = [
+        ([KeyCode::W, KeyCode::Up], Point::new(0.0, -1.0)),
+        ([KeyCode::S, KeyCode::Down], Point::new(0.0, 1.0)),
+        ([KeyCode::A, KeyCode::Left], Point::new(-1.0, 0.0)),
+        ([KeyCode::D, KeyCode::Right], Point::new(1.0, 0.0)),
+    ]
+    .into_iter()
+    .filter(|(keys, _)| keys.iter().any(|key| is_key_down(*key)))
+    .fold(Point::new(0.0, 0.0), |sum, (_, step)| sum + step);
+    direction.normalized()
 }
 
-fn get_random_point(grid_size: usize) -> Point<f32> {
+pub struct GenerationAnimator {
+    cell_size: Point<usize>,
+    offset: Point<f32>,
+    walls: Vec<Line<usize>>,
+    carved: Vec<Line<usize>>,
+    started: f64,
+}
+
+impl GenerationAnimator {
+    pub fn new(settings: &GameSettings) -> Self {
+        let cell_size = settings.cell_size();
+        let mut rng = Rng::new(rand::rand() as u64);
+        let (walls, carved) = make_labyrinth(
+            &mut rng,
+            grid_size(settings.dimensions, cell_size),
+            settings.algorithm,
+            settings.dropout,
+            settings.braid,
+        );
+        Self {
+            cell_size,
+            offset: grid_offset(settings.dimensions, cell_size),
+            walls,
+            carved,
+            started: macroquad::miniquad::date::now(),
+        }
+    }
+
+    pub fn update(&mut self, settings: &GameSettings) {
+        let elapsed = macroquad::miniquad::date::now() - self.started;
+        if elapsed > GENERATION_ANIMATION + GENERATION_HOLD {
+            *self = Self::new(settings);
+        }
+    }
+
+    pub fn walls(&self) -> impl Iterator<Item = Line<f32>> + '_ {
+        let elapsed = macroquad::miniquad::date::now() - self.started;
+        let progress = (elapsed / GENERATION_ANIMATION).min(1.0);
+        let removed = (progress * self.carved.len() as f64) as usize;
+        self.walls
+            .iter()
+            .chain(&self.carved[removed..])
+            .map(|&line| Line::<f32>::from(line.scale(self.cell_size)).translate(self.offset))
+    }
+}
+
+fn make_keys_and_doors(rng: &mut Rng, grid: &Grid, spawn: &Point<f32>) -> (Vec<Door>, Vec<Key>) {
+    let mut passages = grid.passages();
+    let doors = KEY_COLORS
+        .iter()
+        .take(passages.len())
+        .map(|&color| Door {
+            edge: passages.swap_remove(rng.index(passages.len())),
+            locked: true,
+            color,
+        })
+        .collect::<Vec<_>>();
+
+    // Key i is reachable once doors 0..i are open, so collecting them in order always works.
+    let start = grid.find(spawn);
+    let keys = (0..doors.len())
+        .map(|i| {
+            let barriers = doors[i..].iter().map(|d| d.edge).collect::<Vec<_>>();
+            let mut reachable = grid
+                .reachable_cells(start, &barriers)
+                .into_iter()
+                .collect::<Vec<_>>();
+            reachable.sort();
+            let (x, y) = reachable[rng.index(reachable.len())];
+            Key {
+                position: cell_center(x, y, grid.cell_size, grid.offset),
+                color: doors[i].color,
+                collected: false,
+            }
+        })
+        .collect();
+
+    (doors, keys)
+}
+
+fn make_target_keys(
+    rng: &mut Rng,
+    grid: &Grid,
+    spawn: &Point<f32>,
+    target: &Point<f32>,
+    count: usize,
+) -> Vec<Point<f32>> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let occupied = [grid.find(spawn).position, grid.find(target).position];
+    let mut cells = grid
+        .reachable_cells(grid.find(spawn), &[])
+        .into_iter()
+        .filter(|&(x, y)| !occupied.contains(&Point::new(x, y)))
+        .collect::<Vec<_>>();
+    cells.sort();
+    (0..count.min(cells.len()))
+        .map(|_| {
+            let (x, y) = cells.swap_remove(rng.index(cells.len()));
+            cell_center(x, y, grid.cell_size, grid.offset)
+        })
+        .collect()
+}
+
+fn make_gates(rng: &mut Rng, grid: &Grid, spawn: &Point<f32>, target: &Point<f32>) -> Vec<Gate> {
+    let start = grid.find(spawn);
+    let goal = grid.find(target).position;
+    let mut passages = grid.passages();
+    let mut gates: Vec<Gate> = Vec::new();
+
+    while gates.len() < GATE_COUNT && !passages.is_empty() {
+        let edge = passages.swap_remove(rng.index(passages.len()));
+        let barriers = gates
+            .iter()
+            .map(|gate| gate.edge)
+            .chain([edge])
+            .collect::<Vec<_>>();
+        // Only gate passages that can all be closed at once without cutting off the target.
+        if grid
+            .reachable_cells(start, &barriers)
+            .contains(&(goal.x, goal.y))
+        {
+            let period = rng.range(GATE_PERIOD, GATE_PERIOD * 2.0);
+            gates.push(Gate {
+                edge,
+                period,
+                phase: rng.range(0.0, period),
+                closed: false,
+            });
+        }
+    }
+
+    gates
+}
+
+fn edge_key(border: &Line<f32>, cell_size: Point<usize>, offset: Point<f32>) -> Line<usize> {
+    let to_cell = |p: Point<f32>| {
+        Point::new(
+            ((p.x - offset.x) / cell_size.x as f32).round() as usize,
+            ((p.y - offset.y) / cell_size.y as f32).round() as usize,
+        )
+    };
+    let (a, b) = (to_cell(border.a), to_cell(border.b));
+    Line::new(a.min(b), a.max(b))
+}
+
+// Whole cells that fit into the maze area, in columns and rows.
+pub fn grid_size(dimensions: Vec2, cell_size: Point<usize>) -> Point<usize> {
     Point::new(
-        rand::rand() as usize % (WINDOW_DIMENSIONS.x as usize / grid_size) * grid_size
-            + grid_size / 2,
-        rand::rand() as usize % (WINDOW_DIMENSIONS.y as usize / grid_size) * grid_size
-            + grid_size / 2,
+        dimensions.x as usize / cell_size.x,
+        dimensions.y as usize / cell_size.y,
+    )
+}
+
+fn grid_offset(dimensions: Vec2, cell_size: Point<usize>) -> Point<f32> {
+    Point::new(
+        dimensions.x as usize % cell_size.x / 2,
+        dimensions.y as usize % cell_size.y / 2,
     )
     .into()
 }
 
-fn make_walls(grid_size: usize, dropout: f32) -> Vec<Line<f32>> {
-    let labyrinth = compress_labyrinth(make_labyrinth(grid_size, dropout));
+fn cell_center(x: usize, y: usize, cell_size: Point<usize>, offset: Point<f32>) -> Point<f32> {
+    Point::<f32>::from(Point::new(
+        x * cell_size.x + cell_size.x / 2,
+        y * cell_size.y + cell_size.y / 2,
+    )) + offset
+}
+
+fn get_farthest_point(grid: &Grid, spawn: &Point<f32>) -> Point<f32> {
+    let start = grid.find(spawn);
+    // The spawn cell itself is always reachable, so it is the fallback.
+    let (x, y) = grid
+        .distances(start, &[])
+        .into_iter()
+        .max_by_key(|&(cell, distance)| (distance, cell))
+        .map_or((start.position.x, start.position.y), |(cell, _)| cell);
+    cell_center(x, y, grid.cell_size, grid.offset)
+}
+
+// Re-rolls until the target is at least `separation` of the maze diagonal away, in cells.
+fn get_separated_point(
+    rng: &mut Rng,
+    grid: &Grid,
+    spawn: &Point<f32>,
+    separation: f32,
+) -> Point<f32> {
+    let Point {
+        x: columns,
+        y: rows,
+    } = grid.size;
+    let minimum = separation * ((columns * columns + rows * rows) as f32).sqrt();
+    let start = Point::<f32>::from(grid.find(spawn).position);
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        let target = get_random_point(rng, grid);
+        let cell = Point::<f32>::from(grid.find(&target).position);
+        if cell.distance(&start) >= minimum {
+            return target;
+        }
+    }
+    // The cell farthest away in a straight line meets the separation whenever any cell can.
+    let distance = |&(x, y): &(usize, usize)| Point::<f32>::from(Point::new(x, y)).distance(&start);
+    let (x, y) = (0..columns)
+        .cartesian_product(0..rows)
+        .max_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .unwrap_or_default();
+    cell_center(x, y, grid.cell_size, grid.offset)
+}
+
+fn get_random_point(rng: &mut Rng, grid: &Grid) -> Point<f32> {
+    let x = rng.index(grid.size.x);
+    let y = rng.index(grid.size.y);
+    cell_center(x, y, grid.cell_size, grid.offset)
+}
+
+fn make_walls(
+    rng: &mut Rng,
+    grid: &Grid,
+    algorithm: Algorithm,
+    dropout: f32,
+    braid: f32,
+) -> Vec<Line<f32>> {
+    let (labyrinth, _) = make_labyrinth(rng, grid.size, algorithm, dropout, braid);
+    let labyrinth = compress_labyrinth(labyrinth);
     labyrinth
         .into_iter()
-        .map(|line| Line::<f32>::from(line * grid_size))
+        .map(|line| Line::<f32>::from(line.scale(grid.cell_size)).translate(grid.offset))
         .collect()
 }
 
-fn make_labyrinth(grid_size: usize, dropout: f32) -> Vec<Line<usize>> {
+fn make_labyrinth(
+    rng: &mut Rng,
+    size: Point<usize>,
+    algorithm: Algorithm,
+    dropout: f32,
+    braid: f32,
+) -> (Vec<Line<usize>>, Vec<Line<usize>>) {
+    let Point {
+        x: columns,
+        y: rows,
+    } = size;
+
+    let mut carved = match algorithm {
+        Algorithm::Kruskal => make_labyrinth_kruskal(rng, columns, rows),
+        Algorithm::Dfs => make_labyrinth_dfs(rng, columns, rows),
+        Algorithm::Prim => make_labyrinth_prim(rng, columns, rows),
+        Algorithm::Wilson => make_labyrinth_wilson(rng, columns, rows),
+    };
+
+    let passages = carved.iter().copied().collect::<HashSet<_>>();
+    let mut inner_edges = grid_edges(columns, rows)
+        .into_iter()
+        .filter(|edge| is_inner(edge, columns, rows) && !passages.contains(edge))
+        .collect::<Vec<_>>();
+
+    let dropout = dropout.clamp(0.0, 1.0);
+    (0..(inner_edges.len() as f32 * dropout) as usize).for_each(|_| {
+        let rng_edge_idx = rng.index(inner_edges.len());
+        carved.push(inner_edges.swap_remove(rng_edge_idx));
+    });
+
+    let passages = carved.iter().copied().collect::<HashSet<_>>();
+    let walls = grid_edges(columns, rows)
+        .into_iter()
+        .filter(|edge| !passages.contains(edge))
+        .collect();
+    let (walls, braided) = braid_labyrinth(rng, walls, columns, rows, braid);
+    carved.extend(braided);
+    let (walls, opened) = connect_labyrinth(walls, columns, rows);
+    carved.extend(opened);
+
+    (walls, carved)
+}
+
+fn make_labyrinth_kruskal(rng: &mut Rng, columns: usize, rows: usize) -> Vec<Line<usize>> {
     type Area = HashSet<Point<usize>>;
     type Edge = (usize, Option<usize>);
 
+    let mut carved = Vec::new();
+
     let mut areas: HashMap<usize, Area> = HashMap::new();
     let mut edges: HashMap<Line<usize>, Edge> = HashMap::new();
 
-    (0..WINDOW_DIMENSIONS.x as usize / grid_size)
-        .cartesian_product(0..WINDOW_DIMENSIONS.y as usize / grid_size)
+    (0..columns)
+        .cartesian_product(0..rows)
         .map(|(x, y)| Point::new(x, y))
         .enumerate()
         .for_each(|(area_id, cell)| {
             areas.insert(area_id, HashSet::from([cell]));
 
-            let new_edges = vec![
-                Line::new(cell, Point::new(cell.x + 1, cell.y)),
-                Line::new(cell, Point::new(cell.x, cell.y + 1)),
-                Line::new(
-                    Point::new(cell.x + 1, cell.y),
-                    Point::new(cell.x + 1, cell.y + 1),
-                ),
-                Line::new(
-                    Point::new(cell.x, cell.y + 1),
-                    Point::new(cell.x + 1, cell.y + 1),
-                ),
-            ];
-
-            for edge in new_edges {
+            for edge in cell_edges(cell) {
                 edges
                     .entry(edge)
                     .and_modify(|e| e.1 = Some(area_id))
@@ -129,45 +1211,256 @@ fn make_labyrinth(grid_size: usize, dropout: f32) -> Vec<Line<usize>> {
     let mut inner_edges = edges
         .iter()
         .filter_map(|(k, v)| v.1.map(|_| *k))
+        .sorted()
         .collect::<Vec<_>>();
 
     while areas.len() > 1 {
         inner_edges.retain(|edge_id| {
-            let edge = edges.get(edge_id).unwrap();
-            edge.0 != edge.1.unwrap()
+            edges
+                .get(edge_id)
+                .is_some_and(|edge| edge.1.is_some_and(|right| edge.0 != right))
         });
+        if inner_edges.is_empty() {
+            break;
+        }
 
-        let rng_edge_idx = rand::rand() as usize % inner_edges.len();
+        let rng_edge_idx = rng.index(inner_edges.len());
         let edge_id = inner_edges.swap_remove(rng_edge_idx);
-        let edge = edges.remove(&edge_id).unwrap();
-        let right_area = areas.remove(&edge.1.unwrap()).unwrap();
+        let Some((left, Some(right))) = edges.remove(&edge_id) else {
+            continue;
+        };
+        carved.push(edge_id);
+        let right_area = areas.remove(&right).unwrap_or_default();
 
-        areas
-            .entry(edge.0)
-            .and_modify(|a| a.extend(right_area.into_iter()));
+        areas.entry(left).and_modify(|a| a.extend(right_area));
 
         edges.iter_mut().for_each(|(_k, v)| {
-            if v.0 == edge.1.unwrap() {
-                v.0 = edge.0
+            if v.0 == right {
+                v.0 = left
             }
-            if v.1.is_some() && v.1.unwrap() == edge.1.unwrap() {
-                v.1 = Some(edge.0)
+            if v.1 == Some(right) {
+                v.1 = Some(left)
             }
         });
     }
 
-    inner_edges = edges
-        .iter()
-        .filter_map(|(k, v)| v.1.map(|_v| *k))
+    carved
+}
+
+fn make_labyrinth_dfs(rng: &mut Rng, columns: usize, rows: usize) -> Vec<Line<usize>> {
+    let mut carved = Vec::new();
+    let start = Point::new(rng.index(columns), rng.index(rows));
+    let mut visited = HashSet::from([start]);
+    let mut stack = vec![start];
+
+    while let Some(&cell) = stack.last() {
+        let unvisited = cell_neighbors(cell, columns, rows)
+            .into_iter()
+            .filter(|next| !visited.contains(next))
+            .collect::<Vec<_>>();
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let next = unvisited[rng.index(unvisited.len())];
+        visited.insert(next);
+        carved.push(wall_between(cell, next));
+        stack.push(next);
+    }
+
+    carved
+}
+
+fn make_labyrinth_prim(rng: &mut Rng, columns: usize, rows: usize) -> Vec<Line<usize>> {
+    let mut carved = Vec::new();
+    let start = Point::new(rng.index(columns), rng.index(rows));
+    let mut visited = HashSet::from([start]);
+    let mut frontier = cell_neighbors(start, columns, rows)
+        .into_iter()
+        .map(|next| (start, next))
         .collect::<Vec<_>>();
 
-    (0..(inner_edges.len() as f32 * dropout) as usize).for_each(|_| {
-        let rng_edge_idx = rand::rand() as usize % inner_edges.len();
-        let edge_id = inner_edges.swap_remove(rng_edge_idx);
-        edges.remove(&edge_id);
-    });
+    while !frontier.is_empty() {
+        let (cell, next) = frontier.swap_remove(rng.index(frontier.len()));
+        if !visited.insert(next) {
+            continue;
+        }
+        carved.push(wall_between(cell, next));
+        frontier.extend(
+            cell_neighbors(next, columns, rows)
+                .into_iter()
+                .filter(|other| !visited.contains(other))
+                .map(|other| (next, other)),
+        );
+    }
+
+    carved
+}
+
+fn make_labyrinth_wilson(rng: &mut Rng, columns: usize, rows: usize) -> Vec<Line<usize>> {
+    let mut carved = Vec::new();
+    let cells = (0..columns)
+        .cartesian_product(0..rows)
+        .map(|(x, y)| Point::new(x, y))
+        .collect::<Vec<_>>();
+    let mut tree = HashSet::from([cells[rng.index(cells.len())]]);
+
+    for &start in &cells {
+        // Only the last exit taken from each cell is kept, which erases the walk's loops.
+        let mut exits = HashMap::new();
+        let mut cell = start;
+        while !tree.contains(&cell) {
+            let neighbors = cell_neighbors(cell, columns, rows);
+            let next = neighbors[rng.index(neighbors.len())];
+            exits.insert(cell, next);
+            cell = next;
+        }
+
+        let mut cell = start;
+        while let Some(&next) = exits.get(&cell).filter(|_| !tree.contains(&cell)) {
+            tree.insert(cell);
+            carved.push(wall_between(cell, next));
+            cell = next;
+        }
+    }
+
+    carved
+}
+
+fn cell_edges(cell: Point<usize>) -> [Line<usize>; 4] {
+    let (x, y) = (cell.x, cell.y);
+    [
+        Line::new(Point::new(x, y), Point::new(x + 1, y)),
+        Line::new(Point::new(x, y), Point::new(x, y + 1)),
+        Line::new(Point::new(x + 1, y), Point::new(x + 1, y + 1)),
+        Line::new(Point::new(x, y + 1), Point::new(x + 1, y + 1)),
+    ]
+}
+
+fn grid_edges(columns: usize, rows: usize) -> Vec<Line<usize>> {
+    (0..columns)
+        .cartesian_product(0..rows)
+        .flat_map(|(x, y)| cell_edges(Point::new(x, y)))
+        .unique()
+        .sorted()
+        .collect()
+}
+
+fn cell_neighbors(cell: Point<usize>, columns: usize, rows: usize) -> Vec<Point<usize>> {
+    let (x, y) = (cell.x, cell.y);
+    [
+        (y > 0).then(|| Point::new(x, y - 1)),
+        (x + 1 < columns).then(|| Point::new(x + 1, y)),
+        (y + 1 < rows).then(|| Point::new(x, y + 1)),
+        (x > 0).then(|| Point::new(x - 1, y)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn wall_between(a: Point<usize>, b: Point<usize>) -> Line<usize> {
+    let (x, y) = (a.x.max(b.x), a.y.max(b.y));
+    match a.x != b.x {
+        true => Line::new(Point::new(x, y), Point::new(x, y + 1)),
+        false => Line::new(Point::new(x, y), Point::new(x + 1, y)),
+    }
+}
 
-    edges.into_keys().collect()
+fn is_inner(wall: &Line<usize>, columns: usize, rows: usize) -> bool {
+    match wall.a.x == wall.b.x {
+        true => wall.a.x > 0 && wall.a.x < columns,
+        false => wall.a.y > 0 && wall.a.y < rows,
+    }
+}
+
+fn braid_labyrinth(
+    rng: &mut Rng,
+    walls: Vec<Line<usize>>,
+    columns: usize,
+    rows: usize,
+    braid: f32,
+) -> (Vec<Line<usize>>, Vec<Line<usize>>) {
+    let mut walls = walls.into_iter().collect::<HashSet<_>>();
+    let mut removed = Vec::new();
+    let cell_walls = |walls: &HashSet<Line<usize>>, (x, y): (usize, usize)| {
+        cell_edges(Point::new(x, y))
+            .into_iter()
+            .filter(|wall| walls.contains(wall))
+            .collect::<Vec<_>>()
+    };
+
+    if braid <= 0.0 {
+        return (walls.into_iter().sorted().collect(), removed);
+    }
+
+    let mut dead_ends = (0..columns)
+        .cartesian_product(0..rows)
+        .filter(|&cell| cell_walls(&walls, cell).len() == 3)
+        .collect::<Vec<_>>();
+    while !dead_ends.is_empty() {
+        let cell = dead_ends.swap_remove(rng.index(dead_ends.len()));
+        if rng.range(0.0, 1.0) >= braid {
+            continue;
+        }
+        let candidates = cell_walls(&walls, cell);
+        if candidates.len() != 3 {
+            continue;
+        }
+        let inner = candidates
+            .into_iter()
+            .filter(|wall| is_inner(wall, columns, rows))
+            .collect::<Vec<_>>();
+        if !inner.is_empty() {
+            let wall = inner[rng.index(inner.len())];
+            walls.remove(&wall);
+            removed.push(wall);
+        }
+    }
+
+    (walls.into_iter().sorted().collect(), removed)
+}
+
+// Opens walls until every cell is reachable, so spawn and target always share a component.
+fn connect_labyrinth(
+    walls: Vec<Line<usize>>,
+    columns: usize,
+    rows: usize,
+) -> (Vec<Line<usize>>, Vec<Line<usize>>) {
+    let mut walls = walls.into_iter().collect::<HashSet<_>>();
+    let mut opened = Vec::new();
+    let start = Point::new(0, 0);
+    let mut reached = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+
+    loop {
+        while let Some(cell) = queue.pop_front() {
+            for next in cell_neighbors(cell, columns, rows) {
+                if !walls.contains(&wall_between(cell, next)) && reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        let Some((cell, next)) = reached
+            .iter()
+            .sorted()
+            .flat_map(|&cell| {
+                cell_neighbors(cell, columns, rows)
+                    .into_iter()
+                    .map(move |next| (cell, next))
+            })
+            .find(|(_, next)| !reached.contains(next))
+        else {
+            break;
+        };
+        let wall = wall_between(cell, next);
+        walls.remove(&wall);
+        opened.push(wall);
+        reached.insert(next);
+        queue.push_back(next);
+    }
+
+    (walls.into_iter().sorted().collect(), opened)
 }
 
 fn compress_labyrinth(mut labyrinth: Vec<Line<usize>>) -> Vec<Line<usize>> {
@@ -190,16 +1483,20 @@ fn compress_labyrinth(mut labyrinth: Vec<Line<usize>>) -> Vec<Line<usize>> {
     zipped_labyrinth
 }
 
+fn ray_length(cell_size: Point<usize>, vision_range: f32) -> f32 {
+    cell_size.x.max(cell_size.y) as f32 * vision_range.clamp(MIN_VISION_RANGE, MAX_VISION_RANGE)
+}
+
 fn get_ray_directions(rays: usize, length: f32) -> Vec<Point<f32>> {
-    (0..360)
-        .step_by(360 / rays)
-        .map(|r| r as f32 / 360.0 * 2.0 * std::f32::consts::PI)
+    let rays = rays.max(1);
+    (0..rays)
+        .map(|r| r as f32 / rays as f32 * 2.0 * std::f32::consts::PI)
         .map(|r| Point::new(r.sin(), r.cos()) * length)
         .collect()
 }
 
 #[derive(PartialEq, Eq, Hash, Debug)]
-enum Direction {
+pub(crate) enum Direction {
     None,
     North,
     East,
@@ -207,7 +1504,7 @@ enum Direction {
     West,
 }
 
-const DIRECTIONS: [Direction; 4] = [
+pub(crate) const DIRECTIONS: [Direction; 4] = [
     Direction::North,
     Direction::East,
     Direction::South,
@@ -227,18 +1524,19 @@ impl Direction {
 }
 
 #[derive(Debug)]
-struct Cell {
+pub(crate) struct Cell {
     position: Point<usize>,
     borders: HashMap<Direction, Line<f32>>,
     walls: HashMap<Direction, Line<f32>>,
 }
 
 impl Cell {
-    fn new(x: usize, y: usize, grid_size: usize) -> Self {
-        let p1 = Point::new(x * grid_size, y * grid_size).into();
-        let p2 = Point::new((x + 1) * grid_size, y * grid_size).into();
-        let p3 = Point::new((x + 1) * grid_size, (y + 1) * grid_size).into();
-        let p4 = Point::new(x * grid_size, (y + 1) * grid_size).into();
+    pub(crate) fn new(x: usize, y: usize, cell_size: Point<usize>, offset: Point<f32>) -> Self {
+        let corner = |x, y| Point::<f32>::from(Point::new(x, y).scale(cell_size)) + offset;
+        let p1 = corner(x, y);
+        let p2 = corner(x + 1, y);
+        let p3 = corner(x + 1, y + 1);
+        let p4 = corner(x, y + 1);
         Self {
             position: Point::new(x, y),
             borders: HashMap::from([
@@ -253,25 +1551,50 @@ impl Cell {
 }
 
 #[derive(Debug)]
-struct Grid {
+pub(crate) struct Grid {
     cells: HashMap<(usize, usize), Cell>,
-    grid_size: usize,
+    size: Point<usize>,
+    cell_size: Point<usize>,
+    offset: Point<f32>,
 }
 
 impl Grid {
-    fn new(grid_size: usize) -> Self {
+    pub(crate) fn new(dimensions: Vec2, cell_size: Point<usize>) -> Self {
+        let size = grid_size(dimensions, cell_size);
+        Self::with_dimensions(
+            size.x,
+            size.y,
+            cell_size,
+            grid_offset(dimensions, cell_size),
+        )
+    }
+
+    pub(crate) fn with_dimensions(
+        columns: usize,
+        rows: usize,
+        cell_size: Point<usize>,
+        offset: Point<f32>,
+    ) -> Self {
         Self {
-            cells: (0..WINDOW_DIMENSIONS.x as usize / grid_size)
-                .cartesian_product(0..WINDOW_DIMENSIONS.y as usize / grid_size)
-                .map(|(x, y)| ((x, y), Cell::new(x, y, grid_size)))
+            cells: (0..columns)
+                .cartesian_product(0..rows)
+                .map(|(x, y)| ((x, y), Cell::new(x, y, cell_size, offset)))
                 .collect(),
-            grid_size,
+            size: Point::new(columns, rows),
+            cell_size,
+            offset,
         }
     }
 
-    fn fill(mut self, walls: &[Line<f32>]) -> Self {
-        for (_, c) in self.cells.iter_mut() {
-            for wall in walls {
+    pub(crate) fn fill(mut self, walls: &[Line<f32>]) -> Self {
+        for wall in walls {
+            // Only the cells along a wall can have it as a border.
+            let columns = self.cell_range(wall.a.x, wall.b.x, self.offset.x, self.cell_size.x);
+            let rows = self.cell_range(wall.a.y, wall.b.y, self.offset.y, self.cell_size.y);
+            for position in columns.cartesian_product(rows) {
+                let Some(c) = self.cells.get_mut(&position) else {
+                    continue;
+                };
                 for direction in DIRECTIONS {
                     if wall.contains(&c.borders[&direction]) {
                         c.walls.insert(direction, *wall);
@@ -283,40 +1606,189 @@ impl Grid {
         self
     }
 
-    fn move_to<'a>(&'a self, cell: &'a Cell, direction: &Direction) -> &'a Cell {
-        match direction {
-            Direction::North => &self.cells[&(cell.position.x, cell.position.y - 1)],
-            Direction::East => &self.cells[&(cell.position.x + 1, cell.position.y)],
-            Direction::South => &self.cells[&(cell.position.x, cell.position.y + 1)],
-            Direction::West => &self.cells[&(cell.position.x - 1, cell.position.y)],
-            Direction::None => cell,
+    fn cell_range(&self, a: f32, b: f32, offset: f32, size: usize) -> RangeInclusive<usize> {
+        let index = |v: f32| ((v - offset) / size as f32).max(0.0) as usize;
+        index(a.min(b)).saturating_sub(1)..=index(a.max(b))
+    }
+
+    fn neighbor(&self, cell: &Cell, direction: &Direction) -> Option<&Cell> {
+        let (x, y) = (cell.position.x, cell.position.y);
+        let position = match direction {
+            Direction::North => (x, y.checked_sub(1)?),
+            Direction::East => (x + 1, y),
+            Direction::South => (x, y + 1),
+            Direction::West => (x.checked_sub(1)?, y),
+            Direction::None => (x, y),
+        };
+        self.cells.get(&position)
+    }
+
+    fn open_neighbors(&self, position: Point<usize>) -> Vec<Point<usize>> {
+        let cell = &self.cells[&(position.x, position.y)];
+        DIRECTIONS
+            .iter()
+            .filter(|dir| self.is_open(cell, dir, &[]))
+            .filter_map(|dir| self.neighbor(cell, dir))
+            .map(|next| next.position)
+            .collect()
+    }
+
+    fn is_open(&self, cell: &Cell, direction: &Direction, barriers: &[Line<f32>]) -> bool {
+        !cell.walls.contains_key(direction)
+            && !barriers
+                .iter()
+                .any(|barrier| barrier.contains(&cell.borders[direction]))
+    }
+
+    fn passages(&self) -> Vec<Line<f32>> {
+        let mut passages = self
+            .cells
+            .values()
+            .flat_map(|cell| {
+                [Direction::East, Direction::South]
+                    .into_iter()
+                    .filter(|dir| self.is_open(cell, dir, &[]))
+                    .filter(|dir| self.neighbor(cell, dir).is_some())
+                    .map(|dir| {
+                        let border = cell.borders[&dir];
+                        match border.a < border.b {
+                            true => border,
+                            false => Line::new(border.b, border.a),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        passages.sort_by(|l1, l2| l1.partial_cmp(l2).unwrap());
+        passages
+    }
+
+    fn distances(&self, start: &Cell, barriers: &[Line<f32>]) -> HashMap<(usize, usize), usize> {
+        let mut distances = HashMap::from([((start.position.x, start.position.y), 0)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            let distance = distances[&(cell.position.x, cell.position.y)];
+            for dir in DIRECTIONS {
+                if !self.is_open(cell, &dir, barriers) {
+                    continue;
+                }
+                if let Some(next) = self.neighbor(cell, &dir)
+                    && !distances.contains_key(&(next.position.x, next.position.y))
+                {
+                    distances.insert((next.position.x, next.position.y), distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    fn solve(&self, start: &Cell, goal: &Cell) -> Option<Vec<Point<usize>>> {
+        let key = |cell: &Cell| (cell.position.x, cell.position.y);
+        let heuristic = |cell: &Cell| {
+            cell.position.x.abs_diff(goal.position.x) + cell.position.y.abs_diff(goal.position.y)
+        };
+        let mut costs = HashMap::from([(key(start), 0)]);
+        let mut previous: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+        let mut open = BinaryHeap::from([Reverse((heuristic(start), key(start)))]);
+        while let Some(Reverse((_, position))) = open.pop() {
+            let cell = &self.cells[&position];
+            if position == key(goal) {
+                let mut path = vec![cell.position];
+                let mut current = position;
+                while let Some(&before) = previous.get(&current) {
+                    path.push(Point::new(before.0, before.1));
+                    current = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let cost = costs[&position] + 1;
+            for dir in DIRECTIONS {
+                if !self.is_open(cell, &dir, &[]) {
+                    continue;
+                }
+                if let Some(next) = self.neighbor(cell, &dir)
+                    && costs.get(&key(next)).is_none_or(|known| cost < *known)
+                {
+                    costs.insert(key(next), cost);
+                    previous.insert(key(next), position);
+                    open.push(Reverse((cost + heuristic(next), key(next))));
+                }
+            }
         }
+        None
+    }
+
+    fn reachable_cells(&self, start: &Cell, barriers: &[Line<f32>]) -> HashSet<(usize, usize)> {
+        self.distances(start, barriers).into_keys().collect()
     }
 
-    fn find(&self, p: &Point<f32>) -> &Cell {
-        &self.cells[&(p.x as usize / self.grid_size, p.y as usize / self.grid_size)]
+    // Whether a point lies in one of the cells, the only points `find` accepts.
+    pub(crate) fn contains(&self, p: &Point<f32>) -> bool {
+        let (x, y) = (p.x - self.offset.x, p.y - self.offset.y);
+        x.is_finite()
+            && y.is_finite()
+            && x >= 0.0
+            && y >= 0.0
+            && self
+                .cells
+                .contains_key(&(x as usize / self.cell_size.x, y as usize / self.cell_size.y))
     }
 
-    fn find_intersection(
+    pub(crate) fn find(&self, p: &Point<f32>) -> &Cell {
+        &self.cells[&(
+            (p.x - self.offset.x) as usize / self.cell_size.x,
+            (p.y - self.offset.y) as usize / self.cell_size.y,
+        )]
+    }
+
+    pub(crate) fn cast_rays(
+        &self,
+        origin: Point<f32>,
+        directions: &[Point<f32>],
+    ) -> Vec<Option<(Point<f32>, Line<f32>)>> {
+        let cell = self.find(&origin);
+        directions
+            .iter()
+            .map(|&direction| {
+                self.find_intersection(
+                    &Line::new(origin, origin + direction),
+                    cell,
+                    Direction::None,
+                )
+            })
+            .collect()
+    }
+
+    pub(crate) fn find_intersection(
         &self,
         line: &Line<f32>,
         cell: &Cell,
         direction: Direction,
-    ) -> Option<Point<f32>> {
+    ) -> Option<(Point<f32>, Line<f32>)> {
         for dir in DIRECTIONS {
             if dir != direction.rev() && line.intersects(&cell.borders[&dir]) {
-                if let Some(w) = cell.walls.get(&dir) {
-                    return line.intersection(w);
-                } else {
-                    let next_cell = self.move_to(cell, &dir);
-                    return self.find_intersection(line, next_cell, dir);
-                }
+                // The outer edge of the grid stops a ray like any wall would.
+                return match (cell.walls.get(&dir), self.neighbor(cell, &dir)) {
+                    (Some(w), _) => line.intersection(w).map(|p| (p, cell.borders[&dir])),
+                    (None, None) => line
+                        .intersection(&cell.borders[&dir])
+                        .map(|p| (p, cell.borders[&dir])),
+                    (None, Some(next_cell)) => self.find_intersection(line, next_cell, dir),
+                };
             }
         }
         None
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum TimerMode {
+    CountUp,
+    CountDown { budget: f64 },
+}
+
 enum GameTimerState {
     Idle,
     Running,
@@ -325,33 +1797,82 @@ enum GameTimerState {
 
 pub struct GameTimer {
     times: Vec<f64>,
+    laps: Vec<f64>,
     instant: Option<f64>,
     state: GameTimerState,
     pub result: Option<f64>,
+    pub practice: bool,
+    pub mode: TimerMode,
 }
 
 impl GameTimer {
     pub fn new() -> Self {
         Self {
             times: Vec::new(),
+            laps: Vec::new(),
             instant: None,
             state: GameTimerState::Idle,
             result: None,
+            practice: false,
+            mode: TimerMode::CountUp,
         }
     }
 
-    pub fn start(&mut self) {
+    pub fn start(&mut self) -> Result<(), TimerError> {
         match self.state {
             GameTimerState::Idle => {
                 self.times = Vec::new();
+                self.laps = Vec::new();
                 self.instant = Some(macroquad::miniquad::date::now());
                 self.state = GameTimerState::Running;
+                Ok(())
             }
-            _ => panic!("Can only start game timer in idle mode!"),
+            _ => Err(TimerError::AlreadyStarted),
         }
     }
 
+    pub fn reset(&mut self) {
+        self.times = Vec::new();
+        self.laps = Vec::new();
+        self.instant = None;
+        self.state = GameTimerState::Idle;
+        self.result = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, GameTimerState::Running)
+    }
+
+    // Splits are taken from the elapsed time, so paused stretches never count towards one.
+    pub fn lap(&mut self) -> f64 {
+        if !self.is_running() {
+            return 0.0;
+        }
+        let split = self.elapsed() - self.laps.iter().sum::<f64>();
+        self.laps.push(split);
+        split
+    }
+
+    pub fn laps(&self) -> &[f64] {
+        &self.laps
+    }
+
+    pub fn add_penalty(&mut self, seconds: f64) {
+        self.times.push(seconds);
+    }
+
     pub fn current(&self) -> f64 {
+        match self.mode {
+            TimerMode::CountUp => self.elapsed(),
+            TimerMode::CountDown { budget } => (budget - self.elapsed()).max(0.0),
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        matches!(self.mode, TimerMode::CountDown { budget } if self.elapsed() >= budget)
+    }
+
+    pub fn elapsed(&self) -> f64 {
         match self.state {
             GameTimerState::Running => match self.instant {
                 Some(i) => self.times.iter().sum::<f64>() + macroquad::miniquad::date::now() - i,
@@ -361,40 +1882,625 @@ impl GameTimer {
         }
     }
 
-    pub fn stop(&mut self) {
-        match self.state {
-            GameTimerState::Running => {
-                self.times
-                    .push(macroquad::miniquad::date::now() - self.instant.unwrap());
+    pub fn stop(&mut self) -> Result<(), TimerError> {
+        match (&self.state, self.instant) {
+            (GameTimerState::Running, Some(instant)) => {
+                self.times.push(macroquad::miniquad::date::now() - instant);
                 self.result = Some(self.times.iter().sum());
                 self.state = GameTimerState::Idle;
+                Ok(())
             }
-            GameTimerState::Paused => {
+            (GameTimerState::Paused, _) => {
                 self.result = Some(self.times.iter().sum());
                 self.state = GameTimerState::Idle;
+                Ok(())
             }
-            GameTimerState::Idle => panic!("Cannot stop idle game timer!"),
+            _ => Err(TimerError::NotStarted),
         }
     }
 
-    pub fn pause(&mut self) {
-        match self.state {
-            GameTimerState::Running => {
-                self.times
-                    .push(macroquad::miniquad::date::now() - self.instant.unwrap());
+    pub fn pause(&mut self) -> Result<(), TimerError> {
+        match (&self.state, self.instant) {
+            (GameTimerState::Running, Some(instant)) => {
+                self.times.push(macroquad::miniquad::date::now() - instant);
                 self.state = GameTimerState::Paused;
+                Ok(())
             }
-            _ => panic!("Can only pause game timer in running state!"),
+            _ => Err(TimerError::NotRunning),
         }
     }
 
-    pub fn resume(&mut self) {
+    pub fn resume(&mut self) -> Result<(), TimerError> {
         match self.state {
             GameTimerState::Paused => {
                 self.instant = Some(macroquad::miniquad::date::now());
                 self.state = GameTimerState::Running;
+                Ok(())
             }
-            _ => panic!("Can only resume game timer in paused mode!"),
+            _ => Err(TimerError::NotPaused),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RAYS;
+    use crate::geometrie::{ApproxEq, EPSILON};
+
+    // Three by three cells of 10px, a wall east of (0, 0) and one east of (1, 1).
+    fn small_grid() -> Grid {
+        let walls = [
+            Line::new(Point::new(10.0, 0.0), Point::new(10.0, 10.0)),
+            Line::new(Point::new(20.0, 10.0), Point::new(20.0, 20.0)),
+        ];
+        Grid::with_dimensions(3, 3, Point::new(10, 10), Point::new(0.0, 0.0)).fill(&walls)
+    }
+
+    fn intersect(grid: &Grid, from: (f32, f32), to: (f32, f32)) -> Option<Point<f32>> {
+        let (from, to) = (Point::from(from), Point::from(to));
+        grid.find_intersection(&Line::new(from, to), grid.find(&from), Direction::None)
+            .map(|(p, _)| p)
+    }
+
+    #[test]
+    fn find_intersection_in_current_cell() {
+        let grid = small_grid();
+        let hit = intersect(&grid, (5.0, 5.0), (15.0, 5.0));
+        assert_eq!(hit, Some(Point::new(10.0, 5.0)));
+    }
+
+    #[test]
+    fn find_intersection_in_neighbor_cell() {
+        let grid = small_grid();
+        let hit = intersect(&grid, (5.0, 15.0), (25.0, 15.0));
+        assert_eq!(hit, Some(Point::new(20.0, 15.0)));
+    }
+
+    #[test]
+    fn find_intersection_without_wall() {
+        let grid = small_grid();
+        assert_eq!(intersect(&grid, (5.0, 25.0), (25.0, 25.0)), None);
+    }
+
+    #[test]
+    fn find_intersection_parallel_to_border() {
+        let grid = small_grid();
+        assert_eq!(intersect(&grid, (11.0, 1.0), (11.0, 9.0)), None);
+        assert_eq!(intersect(&grid, (5.0, 15.0), (5.0, 28.0)), None);
+    }
+
+    #[test]
+    fn rays_from_corner_cells_stop_at_the_edge() {
+        let grid = Grid::with_dimensions(3, 3, Point::new(10, 10), Point::new(0.0, 0.0));
+        let directions = get_ray_directions(360, 100.0);
+        for origin in [(1.0, 1.0), (29.0, 1.0), (1.0, 29.0), (29.0, 29.0)] {
+            for hit in grid.cast_rays(Point::from(origin), &directions) {
+                let (p, _) = hit.expect("every ray leaves the grid");
+                assert!((-EPSILON..=30.0 + EPSILON).contains(&p.x), "{}", p);
+                assert!((-EPSILON..=30.0 + EPSILON).contains(&p.y), "{}", p);
+            }
+        }
+        // Standing right on the outer corner must not panic either.
+        grid.cast_rays(Point::new(0.0, 0.0), &directions);
+    }
+
+    fn test_settings(labyrinth_size: usize) -> GameSettings {
+        let mut settings = GameSettings::new(true, false, labyrinth_size, 0.1, 10.0, 10.0);
+        settings.wall_memory = false;
+        settings
+    }
+
+    // The original fill, which tests every wall against every cell.
+    fn fill_every_cell(mut grid: Grid, walls: &[Line<f32>]) -> Grid {
+        for c in grid.cells.values_mut() {
+            for wall in walls {
+                for direction in DIRECTIONS {
+                    if wall.contains(&c.borders[&direction]) {
+                        c.walls.insert(direction, *wall);
+                        break;
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    // The original casting, which looks up the starting cell for every ray.
+    fn cast_each_ray(
+        grid: &Grid,
+        origin: Point<f32>,
+        directions: &[Point<f32>],
+    ) -> Vec<Option<(Point<f32>, Line<f32>)>> {
+        directions
+            .iter()
+            .map(|&direction| {
+                let line = Line::new(origin, origin + direction);
+                grid.find_intersection(&line, grid.find(&origin), Direction::None)
+            })
+            .collect()
+    }
+
+    fn origins(grid: &Grid, count: usize) -> Vec<Point<f32>> {
+        let mut rng = Rng::new(7);
+        (0..count)
+            .map(|_| get_random_point(&mut rng, grid))
+            .collect()
+    }
+
+    #[test]
+    fn fill_matches_every_cell_fill() {
+        let game = Game::with_seed(&test_settings(2), 1);
+        let size = game.grid.size;
+        let empty = || Grid::with_dimensions(size.x, size.y, game.cell_size, game.offset);
+        let reference = fill_every_cell(empty(), &game.walls);
+        let grid = empty().fill(&game.walls);
+        for (position, cell) in &reference.cells {
+            assert_eq!(grid.cells[position].walls, cell.walls, "{:?}", position);
+        }
+    }
+
+    #[test]
+    fn cast_rays_matches_casting_each_ray() {
+        let game = Game::with_seed(&test_settings(2), 1);
+        let directions = get_ray_directions(RAYS, 400.0);
+        for origin in origins(&game.grid, 50) {
+            let batch = game.grid.cast_rays(origin, &directions);
+            let single = cast_each_ray(&game.grid, origin, &directions);
+            for (a, b) in batch.iter().zip(&single) {
+                match (a, b) {
+                    (Some((p, _)), Some((q, _))) => assert!(p.approx_eq(q, EPSILON)),
+                    _ => assert_eq!(a.is_none(), b.is_none()),
+                }
+            }
+        }
+    }
+
+    // Timing is only meaningful optimized: cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn bench_cast_rays() {
+        let game = Game::with_seed(&test_settings(2), 1);
+        let directions = get_ray_directions(RAYS, 400.0);
+        let origins = origins(&game.grid, 200);
+        let size = game.grid.size;
+        let empty = || Grid::with_dimensions(size.x, size.y, game.cell_size, game.offset);
+
+        let time = |f: &dyn Fn()| {
+            let start = std::time::Instant::now();
+            (0..5).for_each(|_| f());
+            start.elapsed() / 5
+        };
+        let fill_every = time(&|| {
+            fill_every_cell(empty(), &game.walls);
+        });
+        let fill_along = time(&|| {
+            empty().fill(&game.walls);
+        });
+        assert!(
+            fill_along < fill_every,
+            "{:?} vs {:?}",
+            fill_along,
+            fill_every
+        );
+        let cast_each = time(&|| {
+            origins.iter().for_each(|&origin| {
+                cast_each_ray(&game.grid, origin, &directions);
+            })
+        });
+        let cast_all = time(&|| {
+            origins.iter().for_each(|&origin| {
+                game.grid.cast_rays(origin, &directions);
+            })
+        });
+        assert!(cast_all < cast_each, "{:?} vs {:?}", cast_all, cast_each);
+    }
+
+    #[test]
+    fn found_target_along_the_whole_step() {
+        let mut game = Game::with_seed(&test_settings(1), 3);
+        game.target_keys.clear();
+        game.target = Point::new(500.0, 300.0);
+        let reach = game.player_radius + game.target_radius;
+        let step = |from: (f32, f32), to: (f32, f32)| Line::new(Point::from(from), Point::from(to));
+
+        game.step = step((400.0, 300.0), (600.0, 300.0));
+        assert!(game.found_target(), "passes through");
+        game.step = step((400.0, 300.0 + reach), (600.0, 300.0 + reach));
+        assert!(game.found_target(), "grazes");
+        game.step = step((400.0, 301.0 + reach), (600.0, 301.0 + reach));
+        assert!(!game.found_target(), "misses");
+        game.step = step((400.0, 300.0), (500.0 - reach - 1.0, 300.0));
+        assert!(!game.found_target(), "stops short");
+    }
+
+    #[test]
+    fn fill_matches_walls_off_by_rounding() {
+        let walls = [Line::new(
+            Point::new(10.000001, 0.0),
+            Point::new(10.000001, 20.0),
+        )];
+        let grid =
+            Grid::with_dimensions(2, 2, Point::new(10, 10), Point::new(0.0, 0.0)).fill(&walls);
+        for position in [(0, 0), (0, 1)] {
+            assert_eq!(
+                grid.cells[&position].walls.get(&Direction::East),
+                Some(&walls[0])
+            );
+        }
+        for position in [(1, 0), (1, 1)] {
+            assert_eq!(
+                grid.cells[&position].walls.get(&Direction::West),
+                Some(&walls[0])
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_stay_inside_the_window() {
+        for dimensions in [crate::WINDOW_DIMENSIONS, vec2(1000.0, 420.0)] {
+            for size in 0..crate::GRID_SIZES.len() {
+                let mut settings = test_settings(size);
+                settings.dimensions = dimensions;
+                let game = Game::with_seed(&settings, size as u64);
+                let bounds = game.bounds();
+                let window =
+                    Aabb::new(Point::new(0.0, 0.0), Point::new(dimensions.x, dimensions.y));
+                assert!(window.contains(&bounds.min) && window.contains(&bounds.max));
+                assert!(bounds.contains(&game.spawn) && bounds.contains(&game.target));
+            }
+        }
+    }
+
+    #[test]
+    fn proximity_checks_match_the_sqrt_distance() {
+        let mut game = Game::with_seed(&test_settings(1), 3);
+        game.target = Point::new(500.0, 300.0);
+        let reach = game.player_radius + game.target_radius;
+        for angle in 0..16 {
+            let direction = Point::new(1.0f32, 0.0).rotate(angle as f32 * 0.4);
+            for offset in -20..=20 {
+                let position = game.target + direction * (reach + offset as f32 * 0.01);
+                let distance = position.distance(&game.target);
+
+                game.target_keys.clear();
+                game.position = position;
+                game.step = Line::new(position, position);
+                assert_eq!(game.found_target(), distance <= reach, "{}", position);
+
+                game.target_keys = vec![game.target];
+                game.collect_keys();
+                assert_eq!(
+                    game.target_keys.is_empty(),
+                    distance < reach,
+                    "{}",
+                    position
+                );
+            }
+        }
+    }
+
+    // Cells reachable from the top left one without crossing any of `walls`.
+    fn reachable(walls: &[Line<usize>], columns: usize, rows: usize) -> usize {
+        let walls = walls.iter().collect::<HashSet<_>>();
+        let start = Point::new(0, 0);
+        let mut reached = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            for next in cell_neighbors(cell, columns, rows) {
+                if !walls.contains(&wall_between(cell, next)) && reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached.len()
+    }
+
+    fn remaining_walls(carved: &[Line<usize>], columns: usize, rows: usize) -> Vec<Line<usize>> {
+        grid_edges(columns, rows)
+            .into_iter()
+            .filter(|edge| !carved.contains(edge))
+            .collect()
+    }
+
+    fn assert_spanning_tree(carved: &[Line<usize>], columns: usize, rows: usize) {
+        assert_eq!(carved.len(), columns * rows - 1);
+        let walls = remaining_walls(carved, columns, rows);
+        assert_eq!(reachable(&walls, columns, rows), columns * rows);
+    }
+
+    #[test]
+    fn kruskal_and_backtracker_connect_every_cell() {
+        for seed in 0..10 {
+            let mut rng = Rng::new(seed);
+            assert_spanning_tree(&make_labyrinth_kruskal(&mut rng, 12, 8), 12, 8);
+            assert_spanning_tree(&make_labyrinth_dfs(&mut rng, 12, 8), 12, 8);
+            assert_spanning_tree(&make_labyrinth_dfs(&mut rng, 1, 9), 1, 9);
+        }
+    }
+
+    #[test]
+    fn prim_connects_every_cell() {
+        for seed in 0..10 {
+            let mut rng = Rng::new(seed);
+            assert_spanning_tree(&make_labyrinth_prim(&mut rng, 12, 8), 12, 8);
+            assert_spanning_tree(&make_labyrinth_prim(&mut rng, 7, 1), 7, 1);
+        }
+    }
+
+    #[test]
+    fn wilson_terminates_with_a_spanning_tree() {
+        for seed in 0..5 {
+            let mut rng = Rng::new(seed);
+            assert_spanning_tree(&make_labyrinth_wilson(&mut rng, 12, 8), 12, 8);
+        }
+        // The largest preset, where the random walks take longest.
+        let size = grid_size(crate::WINDOW_DIMENSIONS, Point::new(20, 20));
+        let carved = make_labyrinth_wilson(&mut Rng::new(1), size.x, size.y);
+        assert_spanning_tree(&carved, size.x, size.y);
+    }
+
+    fn dead_ends(walls: &[Line<usize>], columns: usize, rows: usize) -> usize {
+        let walls = walls.iter().collect::<HashSet<_>>();
+        (0..columns)
+            .cartesian_product(0..rows)
+            .map(|(x, y)| Point::new(x, y))
+            .filter(|&cell| {
+                cell_neighbors(cell, columns, rows)
+                    .into_iter()
+                    .filter(|&next| !walls.contains(&wall_between(cell, next)))
+                    .count()
+                    == 1
+            })
+            .count()
+    }
+
+    #[test]
+    fn full_braid_leaves_no_dead_ends() {
+        for (_, algorithm) in crate::ALGORITHMS {
+            for seed in 0..5 {
+                let size = Point::new(12, 8);
+                let (walls, _) = make_labyrinth(&mut Rng::new(seed), size, algorithm, 0.0, 0.0);
+                assert!(dead_ends(&walls, size.x, size.y) > 0);
+                let (walls, _) = make_labyrinth(&mut Rng::new(seed), size, algorithm, 0.0, 1.0);
+                assert_eq!(dead_ends(&walls, size.x, size.y), 0);
+                let (half, _) = make_labyrinth(&mut Rng::new(seed), size, algorithm, 0.0, 0.5);
+                assert!(dead_ends(&half, size.x, size.y) > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn perfect_maze_stats() {
+        let mut settings = test_settings(0);
+        settings.dropout = 0.0;
+        for (_, algorithm) in crate::ALGORITHMS {
+            settings.algorithm = algorithm;
+            let game = Game::with_seed(&settings, 4);
+            let stats = &game.stats;
+            assert_eq!(stats.cells, game.grid.size.x * game.grid.size.y);
+            assert_eq!(stats.passages, stats.cells - 1);
+            assert!(stats.dead_ends > 0);
+            let solution = stats.solution_length.expect("a perfect maze is connected");
+            assert!(solution > 0 && solution <= stats.diameter);
+            assert!(stats.diameter < stats.cells);
+        }
+    }
+
+    #[test]
+    fn connect_opens_enclosed_cells() {
+        let (walls, opened) = connect_labyrinth(grid_edges(6, 4), 6, 4);
+        assert_eq!(opened.len(), 6 * 4 - 1);
+        assert_eq!(reachable(&walls, 6, 4), 6 * 4);
+    }
+
+    #[test]
+    fn target_is_always_reachable() {
+        let mut settings = test_settings(0);
+        for (_, algorithm) in crate::ALGORITHMS {
+            settings.algorithm = algorithm;
+            for (seed, dropout) in (0..10).cartesian_product([0.0, 0.5, 0.9, 1.0]) {
+                settings.dropout = dropout;
+                let game = Game::with_seed(&settings, seed);
+                assert!(game.stats.solution_length.is_some());
+                let reachable = game.grid.reachable_cells(game.grid.find(&game.spawn), &[]);
+                assert_eq!(reachable.len(), game.grid.cells.len());
+            }
+        }
+    }
+
+    #[test]
+    fn targets_keep_their_distance_from_spawn() {
+        for (size, separation) in [(0, 0.25), (1, 0.25), (0, 0.45), (1, 0.45)] {
+            let mut settings = test_settings(size);
+            settings.min_separation = separation;
+            for seed in 0..20 {
+                let game = Game::with_seed(&settings, seed);
+                let diagonal = Point::<f32>::from(game.grid.size).norm();
+                let spawn = Point::<f32>::from(game.grid.find(&game.spawn).position);
+                let target = Point::<f32>::from(game.grid.find(&game.target).position);
+                assert!(
+                    spawn.distance(&target) >= separation * diagonal,
+                    "seed {}",
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rays_are_evenly_spaced() {
+        for count in [90, 360, 500, 7] {
+            let rays = get_ray_directions(count, 50.0);
+            assert_eq!(rays.len(), count);
+            let step = 2.0 * std::f32::consts::PI / count as f32;
+            for (a, b) in rays.iter().zip(rays.iter().cycle().skip(1)) {
+                assert!((a.norm() - 50.0).abs() < 1e-3);
+                let angle = a.cross(*b).atan2(a.dot(*b)).abs();
+                assert!((angle - step).abs() < 1e-4, "{} rays", count);
+            }
+        }
+        assert_eq!(get_ray_directions(0, 50.0).len(), 1);
+    }
+
+    #[test]
+    fn timer_rejects_illegal_transitions() {
+        let mut timer = GameTimer::new();
+        assert_eq!(timer.stop(), Err(TimerError::NotStarted));
+        assert_eq!(timer.pause(), Err(TimerError::NotRunning));
+        assert_eq!(timer.resume(), Err(TimerError::NotPaused));
+        assert_eq!(timer.lap(), 0.0);
+
+        assert_eq!(timer.start(), Ok(()));
+        assert_eq!(timer.start(), Err(TimerError::AlreadyStarted));
+        assert_eq!(timer.resume(), Err(TimerError::NotPaused));
+        assert_eq!(timer.pause(), Ok(()));
+        assert_eq!(timer.pause(), Err(TimerError::NotRunning));
+        assert_eq!(timer.start(), Err(TimerError::AlreadyStarted));
+        assert_eq!(timer.resume(), Ok(()));
+        assert_eq!(timer.stop(), Ok(()));
+        assert!(timer.result.is_some_and(|time| time >= 0.0));
+        assert_eq!(timer.stop(), Err(TimerError::NotStarted));
+        assert_eq!(timer.start(), Ok(()));
+    }
+
+    #[test]
+    fn timer_laps_add_up_to_the_elapsed_time() {
+        let mut timer = GameTimer::new();
+        timer.start().unwrap();
+        (0..3).for_each(|_| {
+            timer.lap();
+        });
+        timer.pause().unwrap();
+        assert_eq!(timer.laps().len(), 3);
+        let total = timer.laps().iter().sum::<f64>();
+        assert!(total >= 0.0 && total <= timer.elapsed());
+        assert_eq!(timer.lap(), 0.0);
+        timer.reset();
+        assert!(timer.laps().is_empty());
+    }
+
+    #[test]
+    fn legal_moves_respect_doors_gates_and_the_grid() {
+        let mut settings = test_settings(0);
+        settings.keys_and_doors = true;
+        settings.dynamic_walls = true;
+        let mut game = Game::with_seed(&settings, 7);
+        assert!(!game.doors.is_empty() && !game.gates.is_empty());
+        let across = |edge: &Line<f32>| {
+            let along = (edge.b - edge.a).normalized();
+            let normal = Point::new(-along.y, along.x) * 2.0;
+            (edge.midpoint() - normal, edge.midpoint() + normal)
+        };
+        game.gates.iter_mut().for_each(|gate| gate.closed = false);
+        let (from, to) = across(&game.doors[0].edge);
+        assert!(!game.is_legal_move(from, to));
+        game.doors.iter_mut().for_each(|door| door.locked = false);
+        assert!(game.is_legal_move(from, to));
+
+        let (from, to) = across(&game.gates[0].edge);
+        assert!(game.is_legal_move(from, to));
+        game.gates[0].closed = true;
+        assert!(!game.is_legal_move(from, to));
+
+        // Off the cells is illegal rather than a lookup out of the grid.
+        let spawn = game.spawn;
+        for outside in [
+            Point::new(-1.0, spawn.y),
+            Point::new(game.dimensions.x + 1.0, spawn.y),
+            Point::new(spawn.x, f32::NAN),
+        ] {
+            assert!(!game.is_legal_move(spawn, outside));
+            assert!(!game.is_legal_move(outside, spawn));
+        }
+    }
+
+    #[test]
+    fn same_seed_same_maze() {
+        let settings = test_settings(1);
+        let (first, second) = (
+            Game::with_seed(&settings, 42),
+            Game::with_seed(&settings, 42),
+        );
+        assert_eq!(first.walls, second.walls);
+        assert_eq!(first.spawn, second.spawn);
+        assert_eq!(first.target, second.target);
+        assert_ne!(first.walls, Game::with_seed(&settings, 43).walls);
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let settings = test_settings(1);
+        let walls = |game: &Game| {
+            game.walls
+                .iter()
+                .map(|wall| format!("{:?}", wall))
+                .collect::<HashSet<_>>()
+        };
+        let mut game = Game::with_seed(&settings, 42);
+        let imported = Game::from_ascii(&settings, &game.to_ascii()).unwrap();
+        assert_eq!(walls(&imported), walls(&game));
+        assert_eq!(imported.to_ascii(), game.to_ascii());
+
+        // Spawn and target sharing a cell survive as 'X'.
+        game.target = game.spawn;
+        let text = game.to_ascii();
+        assert_eq!(text.matches('X').count(), 1);
+        let imported = Game::from_ascii(&settings, &text).unwrap();
+        assert_eq!(imported.spawn, imported.target);
+    }
+
+    #[test]
+    fn farthest_placement_maximizes_path_distance() {
+        let mut settings = test_settings(1);
+        settings.placement = Placement::Farthest;
+        for seed in [1, 2, 3] {
+            let game = Game::with_seed(&settings, seed);
+            let distances = game.grid.distances(game.grid.find(&game.spawn), &[]);
+            let target = game.grid.find(&game.target).position;
+            assert_eq!(
+                distances.get(&(target.x, target.y)),
+                distances.values().max(),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn uneven_dimensions_center_the_grid() {
+        let (dimensions, cell_size) = (vec2(1000.0, 420.0), Point::new(45, 45));
+        let grid = Grid::new(dimensions, cell_size);
+        assert_eq!(grid.size, Point::new(22, 9));
+        // 10 and 15 pixels are left over, split evenly with the odd one on the far side.
+        assert_eq!(grid.offset, Point::new(5.0, 7.0));
+        let far = Point::new(5.0 + 22.0 * 45.0, 7.0 + 9.0 * 45.0);
+        assert!(dimensions.x - far.x - grid.offset.x <= 1.0);
+        assert!(dimensions.y - far.y - grid.offset.y <= 1.0);
+
+        for (x, y) in [(0, 0), (21, 0), (0, 8), (21, 8)] {
+            let center = cell_center(x, y, cell_size, grid.offset);
+            assert!(grid.contains(&center));
+            assert_eq!(grid.find(&center).position, Point::new(x, y));
+        }
+        for outside in [
+            Point::new(4.0, 100.0),
+            Point::new(100.0, 6.0),
+            Point::new(far.x + 1.0, 100.0),
+            Point::new(100.0, far.y + 1.0),
+        ] {
+            assert!(!grid.contains(&outside), "{}", outside);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn walls_serde_round_trip() {
+        let game = Game::with_seed(&test_settings(1), 42);
+        let text = serde_json::to_string(&game.walls).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<Line<f32>>>(&text).unwrap(),
+            game.walls
+        );
+    }
+}
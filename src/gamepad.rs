@@ -0,0 +1,129 @@
+#[cfg(feature = "gamepad")]
+use gilrs_core::{EvCode, EventType, IS_Y_AXIS_REVERSED, native_ev_codes as codes};
+#[cfg(feature = "gamepad")]
+use macroquad::prelude::{Vec2, get_last_key_pressed, mouse_delta_position};
+
+use crate::geometrie::Point;
+
+// Only a backend ever navigates, so without one the variants go unused.
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Navigation {
+    Up,
+    Down,
+    Activate,
+}
+
+pub trait Gamepad {
+    fn poll(&mut self);
+    fn left_stick(&self) -> Option<Point<f32>>;
+    fn navigation(&self) -> Option<Navigation>;
+}
+
+// The pad of the platform if the backend is built in and finds one, no pad otherwise.
+pub fn connect() -> Box<dyn Gamepad> {
+    #[cfg(feature = "gamepad")]
+    if let Some(gilrs) = Gilrs::new() {
+        return Box::new(gilrs);
+    }
+    Box::new(NoGamepad)
+}
+
+// macroquad does not expose controllers yet, so without a backend no pad is ever connected.
+pub struct NoGamepad;
+
+impl Gamepad for NoGamepad {
+    fn poll(&mut self) {}
+
+    fn left_stick(&self) -> Option<Point<f32>> {
+        None
+    }
+
+    fn navigation(&self) -> Option<Navigation> {
+        None
+    }
+}
+
+#[cfg(feature = "gamepad")]
+pub struct Gilrs {
+    gilrs: gilrs_core::Gilrs,
+    stick: Option<Point<f32>>,
+    navigation: Option<Navigation>,
+}
+
+#[cfg(feature = "gamepad")]
+impl Gilrs {
+    pub fn new() -> Option<Self> {
+        Some(Self {
+            gilrs: gilrs_core::Gilrs::new().ok()?,
+            stick: None,
+            navigation: None,
+        })
+    }
+
+    // Raw axis values mapped onto -1..1, with y pointing down the screen like everywhere else.
+    fn axis(&self, id: usize, value: i32, code: EvCode) -> f32 {
+        let Some(info) = self.gilrs.gamepad(id).and_then(|pad| pad.axis_info(code)) else {
+            return 0.0;
+        };
+        let range = info.max as f32 - info.min as f32;
+        if range <= 0.0 {
+            return 0.0;
+        }
+        let value = ((value as f32 - info.min as f32) / range * 2.0 - 1.0).clamp(-1.0, 1.0);
+        match IS_Y_AXIS_REVERSED || (code != codes::AXIS_LSTICKY && code != codes::AXIS_DPADY) {
+            true => value,
+            false => -value,
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl Gamepad for Gilrs {
+    fn poll(&mut self) {
+        self.navigation = None;
+        // The stick only steers while it was used last, the mouse or a key takes over again.
+        if mouse_delta_position() != Vec2::ZERO || get_last_key_pressed().is_some() {
+            self.stick = None;
+        }
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::AxisValueChanged(value, code) if code == codes::AXIS_LSTICKX => {
+                    let y = self.stick.map_or(0.0, |stick| stick.y);
+                    self.stick = Some(Point::new(self.axis(event.id, value, code), y));
+                }
+                EventType::AxisValueChanged(value, code) if code == codes::AXIS_LSTICKY => {
+                    let x = self.stick.map_or(0.0, |stick| stick.x);
+                    self.stick = Some(Point::new(x, self.axis(event.id, value, code)));
+                }
+                EventType::AxisValueChanged(value, code) if code == codes::AXIS_DPADY => {
+                    let y = self.axis(event.id, value, code);
+                    if y < 0.0 {
+                        self.navigation = Some(Navigation::Up);
+                    } else if y > 0.0 {
+                        self.navigation = Some(Navigation::Down);
+                    }
+                }
+                EventType::ButtonPressed(code) if code == codes::BTN_DPAD_UP => {
+                    self.navigation = Some(Navigation::Up);
+                }
+                EventType::ButtonPressed(code) if code == codes::BTN_DPAD_DOWN => {
+                    self.navigation = Some(Navigation::Down);
+                }
+                EventType::ButtonPressed(code) if code == codes::BTN_SOUTH => {
+                    self.navigation = Some(Navigation::Activate);
+                }
+                EventType::Disconnected => self.stick = None,
+                _ => {}
+            }
+        }
+    }
+
+    fn left_stick(&self) -> Option<Point<f32>> {
+        self.stick
+    }
+
+    fn navigation(&self) -> Option<Navigation> {
+        self.navigation
+    }
+}
@@ -1,17 +1,34 @@
-use std::ops::{Add, Div, Mul, MulAssign, Sub};
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 #[derive(Hash, PartialEq, PartialOrd, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point<T> {
     pub x: T,
     pub y: T,
 }
 
 #[derive(Hash, PartialEq, PartialOrd, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Line<T> {
     pub a: Point<T>,
     pub b: Point<T>,
 }
 
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Circle<T> {
+    pub center: Point<T>,
+    pub radius: T,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb<T> {
+    pub min: Point<T>,
+    pub max: Point<T>,
+}
+
 impl<T> Point<T> {
     pub fn new(x: T, y: T) -> Self {
         Self { x, y }
@@ -30,6 +47,16 @@ impl<T> Point<T> {
     {
         self.x * other.y - self.y * other.x
     }
+
+    pub fn scale(self, other: Self) -> Self
+    where
+        T: Mul<Output = T>,
+    {
+        Self {
+            x: self.x * other.x,
+            y: self.y * other.y,
+        }
+    }
 }
 
 impl<T> From<(T, T)> for Point<T> {
@@ -41,6 +68,18 @@ impl<T> From<(T, T)> for Point<T> {
     }
 }
 
+impl<T: fmt::Display> fmt::Display for Point<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Line<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}->{}]", self.a, self.b)
+    }
+}
+
 impl From<Point<usize>> for Point<f32> {
     fn from(p: Point<usize>) -> Self {
         Self {
@@ -59,24 +98,81 @@ impl From<Line<usize>> for Line<f32> {
     }
 }
 
+pub const EPSILON: f32 = 1e-3;
+
+pub trait ApproxEq {
+    fn approx_eq(&self, other: &Self, eps: f32) -> bool;
+}
+
+impl ApproxEq for usize {
+    fn approx_eq(&self, other: &Self, _eps: f32) -> bool {
+        self == other
+    }
+}
+
+impl ApproxEq for f32 {
+    fn approx_eq(&self, other: &Self, eps: f32) -> bool {
+        (self - other).abs() <= eps
+    }
+}
+
+impl<T: ApproxEq> ApproxEq for Point<T> {
+    fn approx_eq(&self, other: &Self, eps: f32) -> bool {
+        self.x.approx_eq(&other.x, eps) & self.y.approx_eq(&other.y, eps)
+    }
+}
+
+impl<T: ApproxEq> ApproxEq for Line<T> {
+    fn approx_eq(&self, other: &Self, eps: f32) -> bool {
+        (self.a.approx_eq(&other.a, eps) & self.b.approx_eq(&other.b, eps))
+            | (self.a.approx_eq(&other.b, eps) & self.b.approx_eq(&other.a, eps))
+    }
+}
+
 #[derive(PartialEq)]
 pub enum Orientation {
     Vertical,
     Horizontal,
 }
 
-impl<T: std::cmp::PartialEq> Line<T> {
+impl<T> Line<T> {
     pub fn new(a: Point<T>, b: Point<T>) -> Self {
         Self { a, b }
     }
+
+    pub fn scale(self, factor: Point<T>) -> Self
+    where
+        T: Mul<Output = T> + Copy,
+    {
+        Self {
+            a: self.a.scale(factor),
+            b: self.b.scale(factor),
+        }
+    }
+
+    pub fn translate(self, offset: Point<T>) -> Self
+    where
+        T: Add<Output = T> + Copy,
+    {
+        Self {
+            a: self.a + offset,
+            b: self.b + offset,
+        }
+    }
+}
+
+impl<T: ApproxEq> Line<T> {
     pub fn orientation(&self) -> Orientation {
-        match self.a.x == self.b.x {
-            true => Orientation::Horizontal,
-            false => Orientation::Vertical,
+        match self.a.x.approx_eq(&self.b.x, EPSILON) {
+            true => Orientation::Vertical,
+            false => Orientation::Horizontal,
         }
     }
     pub fn shares_endpoint(&self, other: &Self) -> bool {
-        (self.a == other.a) | (self.a == other.b) | (self.b == other.a) | (self.b == other.b)
+        self.a.approx_eq(&other.a, EPSILON)
+            | self.a.approx_eq(&other.b, EPSILON)
+            | self.b.approx_eq(&other.a, EPSILON)
+            | self.b.approx_eq(&other.b, EPSILON)
     }
     pub fn extends(&self, other: &Self) -> bool {
         self.shares_endpoint(other) & (self.orientation() == other.orientation())
@@ -85,18 +181,21 @@ impl<T: std::cmp::PartialEq> Line<T> {
 
 impl<T> Line<T>
 where
-    T: num_traits::real::Real + std::cmp::PartialEq,
+    T: num_traits::real::Real + ApproxEq,
 {
     fn ccw(a: &Point<T>, b: &Point<T>, c: &Point<T>) -> bool {
         (c.y - a.y) * (b.x - a.x) > (b.y - a.y) * (c.x - a.x)
     }
 
     pub fn contains(&self, other: &Self) -> bool {
+        let eps = T::from(EPSILON).unwrap();
+        let xs = self.a.x - eps..=self.b.x + eps;
+        let ys = self.a.y - eps..=self.b.y + eps;
         (self.orientation() == other.orientation())
-            & (self.a.x..=self.b.x).contains(&other.a.x)
-            & (self.a.y..=self.b.y).contains(&other.a.y)
-            & (self.a.x..=self.b.x).contains(&other.b.x)
-            & (self.a.y..=self.b.y).contains(&other.b.y)
+            & xs.contains(&other.a.x)
+            & ys.contains(&other.a.y)
+            & xs.contains(&other.b.x)
+            & ys.contains(&other.b.y)
     }
 
     pub fn intersects(&self, other: &Self) -> bool {
@@ -108,6 +207,35 @@ where
             & (Self::ccw(p1, p2, p3) != Self::ccw(p1, p2, p4))
     }
 
+    pub fn midpoint(&self) -> Point<T> {
+        (self.a + self.b) / (T::one() + T::one())
+    }
+
+    pub fn length(&self) -> T {
+        self.a.distance(&self.b)
+    }
+
+    // Projects `p` onto the line, clamped to the segment between its endpoints.
+    pub fn closest_point(&self, p: &Point<T>) -> Point<T> {
+        let direction = self.b - self.a;
+        let length = direction.snorm();
+        if length.is_zero() {
+            return self.a;
+        }
+        let t = (*p - self.a).dot(direction) / length;
+        self.a.lerp(&self.b, t.max(T::zero()).min(T::one()))
+    }
+
+    pub fn distance_to_point(&self, p: &Point<T>) -> T {
+        self.closest_point(p).distance(p)
+    }
+
+    // Mirrors a direction across the line, the way light bounces off a wall.
+    pub fn reflect(&self, incoming: Point<T>) -> Point<T> {
+        let axis = (self.b - self.a).normalized();
+        axis * (incoming.dot(axis) * (T::one() + T::one())) - incoming
+    }
+
     pub fn intersection(&self, other: &Self) -> Option<Point<T>> {
         let p1 = &self.a;
         let p2 = &self.b;
@@ -129,6 +257,89 @@ where
     }
 }
 
+impl<T> Circle<T>
+where
+    T: num_traits::real::Real + ApproxEq,
+{
+    pub fn new(center: Point<T>, radius: T) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains(&self, p: &Point<T>) -> bool {
+        (*p - self.center).snorm() <= self.radius.powi(2)
+    }
+
+    // Where the segment crosses the outline, the one closest to `line.a` first.
+    pub fn line_intersections(&self, line: &Line<T>) -> Vec<Point<T>> {
+        let direction = line.b - line.a;
+        let from_center = line.a - self.center;
+        let a = direction.dot(direction);
+        let b = from_center.dot(direction) * (T::one() + T::one());
+        let c = from_center.dot(from_center) - self.radius.powi(2);
+        let discriminant = b.powi(2) - a * c * T::from(4.0).unwrap();
+        if a.is_zero() || discriminant < T::zero() {
+            return Vec::new();
+        }
+        let root = discriminant.sqrt();
+        // A tangent line touches the outline only once.
+        let roots = match root.is_zero() {
+            true => vec![-b / (a + a)],
+            false => vec![(-b - root) / (a + a), (-b + root) / (a + a)],
+        };
+        roots
+            .into_iter()
+            .filter(|t| (T::zero()..=T::one()).contains(t))
+            .map(|t| line.a + direction * t)
+            .collect()
+    }
+
+    // Also true for a segment that lies completely inside the circle.
+    pub fn intersects_line(&self, line: &Line<T>) -> bool {
+        self.contains(&line.a)
+            || self.contains(&line.b)
+            || !self.line_intersections(line).is_empty()
+    }
+}
+
+impl<T> Aabb<T>
+where
+    T: num_traits::real::Real + ApproxEq,
+{
+    pub fn new(min: Point<T>, max: Point<T>) -> Self {
+        Self { min, max }
+    }
+
+    // Without any points the box is inverted and contains nothing.
+    pub fn from_points(points: &[Point<T>]) -> Self {
+        let empty = Self::new(
+            Point::new(T::max_value(), T::max_value()),
+            Point::new(T::min_value(), T::min_value()),
+        );
+        points.iter().fold(empty, |bounds, p| {
+            Self::new(
+                Point::new(bounds.min.x.min(p.x), bounds.min.y.min(p.y)),
+                Point::new(bounds.max.x.max(p.x), bounds.max.y.max(p.y)),
+            )
+        })
+    }
+
+    pub fn contains(&self, p: &Point<T>) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x) && (self.min.y..=self.max.y).contains(&p.y)
+    }
+
+    pub fn intersects_line(&self, line: &Line<T>) -> bool {
+        let corners = [
+            self.min,
+            Point::new(self.max.x, self.min.y),
+            self.max,
+            Point::new(self.min.x, self.max.y),
+        ];
+        self.contains(&line.a)
+            || self.contains(&line.b)
+            || (0..4).any(|i| line.intersects(&Line::new(corners[i], corners[(i + 1) % 4])))
+    }
+}
+
 impl<T> Add for Point<T>
 where
     T: Add<Output = T>,
@@ -157,6 +368,40 @@ where
     }
 }
 
+impl<T> Neg for Point<T>
+where
+    T: Neg<Output = T>,
+{
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Point {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl<T> AddAssign for Point<T>
+where
+    T: AddAssign,
+{
+    fn add_assign(&mut self, other: Self) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl<T> SubAssign for Point<T>
+where
+    T: SubAssign,
+{
+    fn sub_assign(&mut self, other: Self) {
+        self.x -= other.x;
+        self.y -= other.y;
+    }
+}
+
 impl<T> Mul<T> for Point<T>
 where
     T: Mul<Output = T> + Copy,
@@ -246,7 +491,299 @@ where
         self.snorm().sqrt()
     }
 
+    // The zero vector has no direction, so it stays zero instead of turning into NaN.
+    pub fn normalized(&self) -> Self {
+        match self.norm() {
+            norm if norm.is_zero() => *self,
+            norm => *self / norm,
+        }
+    }
+
+    // Counterclockwise in a y-up frame, which is clockwise on screen.
+    pub fn rotate(&self, radians: T) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Point::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    pub fn rotate_around(&self, center: &Self, radians: T) -> Self {
+        (*self - *center).rotate(radians) + *center
+    }
+
+    pub fn lerp(&self, other: &Self, t: T) -> Self {
+        *self + (*other - *self) * t
+    }
+
     pub fn distance(&self, other: &Self) -> T {
         (*other - *self).norm()
     }
+
+    pub fn manhattan(&self, other: &Self) -> T {
+        (other.x - self.x).abs() + (other.y - self.y).abs()
+    }
+
+    pub fn chebyshev(&self, other: &Self) -> T {
+        (other.x - self.x).abs().max((other.y - self.y).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_endpoint_within_epsilon() {
+        let a = Line::new(Point::new(0.0f32, 0.0), Point::new(10.0, 0.0));
+        let b = Line::new(Point::new(10.000001f32, 0.0), Point::new(20.0, 0.0));
+        assert!(a.shares_endpoint(&b));
+        assert!(a.extends(&b));
+        let c = Line::new(Point::new(10.1f32, 0.0), Point::new(20.0, 0.0));
+        assert!(!a.shares_endpoint(&c));
+        let d = Line::new(Point::new(10.000001f32, 0.0), Point::new(10.0, 10.0));
+        assert!(a.shares_endpoint(&d) && !a.extends(&d));
+    }
+
+    #[test]
+    fn usize_points_compare_exactly() {
+        assert!(Point::new(1usize, 2).approx_eq(&Point::new(1, 2), 1.0));
+        assert!(!Point::new(1usize, 2).approx_eq(&Point::new(1, 3), 1.0));
+    }
+
+    #[test]
+    fn midpoint_and_length() {
+        let horizontal = Line::new(Point::new(2.0f32, 3.0), Point::new(8.0, 3.0));
+        assert_eq!(horizontal.midpoint(), Point::new(5.0, 3.0));
+        assert_eq!(horizontal.length(), 6.0);
+        let vertical = Line::new(Point::new(1.0f32, -4.0), Point::new(1.0, 4.0));
+        assert_eq!(vertical.midpoint(), Point::new(1.0, 0.0));
+        assert_eq!(vertical.length(), 8.0);
+        let diagonal = Line::new(Point::new(0.0f32, 0.0), Point::new(3.0, 4.0));
+        assert_eq!(diagonal.midpoint(), Point::new(1.5, 2.0));
+        assert_eq!(diagonal.length(), 5.0);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Point::new(1.5f32, -2.0).to_string(), "(1.5, -2)");
+        let line = Line::new(Point::new(0usize, 1), Point::new(2, 3));
+        assert_eq!(line.to_string(), "[(0, 1)->(2, 3)]");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let point = Point::new(1.25f32, -3.5);
+        let text = serde_json::to_string(&point).unwrap();
+        assert_eq!(text, r#"{"x":1.25,"y":-3.5}"#);
+        assert_eq!(serde_json::from_str::<Point<f32>>(&text).unwrap(), point);
+
+        let line = Line::new(Point::new(0usize, 7), Point::new(4, 7));
+        let text = serde_json::to_string(&line).unwrap();
+        assert_eq!(serde_json::from_str::<Line<usize>>(&text).unwrap(), line);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_walls() {
+        let walls = vec![
+            Line::new(Point::new(0.0f32, 0.0), Point::new(100.0, 0.0)),
+            Line::new(Point::new(100.0f32, 0.0), Point::new(100.0, 33.333332)),
+            Line::new(Point::new(12.5f32, 50.0), Point::new(12.5, 50.0)),
+        ];
+        let text = serde_json::to_string(&walls).unwrap();
+        assert!(text.starts_with(r#"[{"a":{"x":0.0,"y":0.0},"b":{"x":100.0,"y":0.0}},"#));
+        assert_eq!(
+            serde_json::from_str::<Vec<Line<f32>>>(&text).unwrap(),
+            walls
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<Line<f32>>>("[]").unwrap(),
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn orientation() {
+        let vertical = Line::new(Point::new(3.0f32, 0.0), Point::new(3.0, 5.0));
+        let horizontal = Line::new(Point::new(0.0f32, 3.0), Point::new(5.0, 3.0));
+        let point = Line::new(Point::new(1.0f32, 1.0), Point::new(1.0, 1.0));
+        assert!(vertical.orientation() == Orientation::Vertical);
+        assert!(horizontal.orientation() == Orientation::Horizontal);
+        assert!(point.orientation() == Orientation::Vertical);
+        assert!(
+            Line::new(Point::new(2usize, 0), Point::new(2, 1)).orientation()
+                == Orientation::Vertical
+        );
+        assert!(vertical.contains(&Line::new(Point::new(3.0, 1.0), Point::new(3.0, 2.0))));
+        assert!(!vertical.contains(&Line::new(Point::new(3.0, 1.0), Point::new(4.0, 1.0))));
+    }
+
+    #[test]
+    fn manhattan_and_chebyshev() {
+        let (a, b) = (Point::new(1.0f32, 2.0), Point::new(4.0, -2.0));
+        assert_eq!(a.manhattan(&b), 7.0);
+        assert_eq!(a.chebyshev(&b), 4.0);
+        assert_eq!(a.distance(&b), 5.0);
+        assert_eq!(b.manhattan(&a), 7.0);
+        assert_eq!(a.chebyshev(&a), 0.0);
+    }
+
+    #[test]
+    fn zero_length_line() {
+        let line = Line::new(Point::new(2.5f32, -1.0), Point::new(2.5, -1.0));
+        assert_eq!(line.length(), 0.0);
+        assert_eq!(line.midpoint(), Point::new(2.5, -1.0));
+        let diagonal = Line::new(Point::new(-1.0f32, -1.0), Point::new(1.0, 1.0));
+        assert_eq!(diagonal.length(), 8.0f32.sqrt());
+        assert_eq!(diagonal.midpoint(), Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn normalized() {
+        assert_eq!(Point::new(3.0f32, -4.0).normalized(), Point::new(0.6, -0.8));
+        assert_eq!(Point::new(0.0f32, 0.0).normalized(), Point::new(0.0, 0.0));
+        assert!((Point::new(1e-3f32, 2e-3).normalized().norm() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rotate() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        let p = Point::new(1.0f32, 0.0);
+        assert!(p.rotate(FRAC_PI_2).approx_eq(&Point::new(0.0, 1.0), 1e-6));
+        assert!(p.rotate(PI).approx_eq(&Point::new(-1.0, 0.0), 1e-6));
+        assert!(p.rotate(2.0 * PI).approx_eq(&p, 1e-6));
+        let center = Point::new(2.0f32, 2.0);
+        let rotated = Point::new(3.0f32, 2.0).rotate_around(&center, -FRAC_PI_2);
+        assert!(rotated.approx_eq(&Point::new(2.0, 1.0), 1e-6));
+        assert!((rotated.distance(&center) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lerp() {
+        let (a, b) = (Point::new(0.0f32, 10.0), Point::new(4.0, -2.0));
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.25), Point::new(1.0, 7.0));
+        assert_eq!(a.lerp(&b, 2.0), Point::new(8.0, -14.0));
+    }
+
+    #[test]
+    fn negation_and_assignment_operators() {
+        let mut p = Point::new(1.5f32, -2.0);
+        assert_eq!(-p, Point::new(-1.5, 2.0));
+        assert_eq!(p + -p, Point::new(0.0, 0.0));
+        p += Point::new(0.5, 4.0);
+        assert_eq!(p, Point::new(2.0, 2.0));
+        p -= Point::new(3.0, 1.0);
+        assert_eq!(p, Point::new(-1.0, 1.0));
+        let mut q = Point::new(3usize, 4);
+        q += Point::new(1, 1);
+        q -= Point::new(4, 0);
+        assert_eq!(q, Point::new(0, 5));
+    }
+
+    #[test]
+    fn circle_and_line() {
+        let circle = Circle::new(Point::new(5.0f32, 5.0), 2.0);
+        let through = Line::new(Point::new(0.0f32, 5.0), Point::new(10.0, 5.0));
+        assert!(circle.intersects_line(&through));
+        assert_eq!(
+            circle.line_intersections(&through),
+            vec![Point::new(3.0, 5.0), Point::new(7.0, 5.0)]
+        );
+        let graze = Line::new(Point::new(0.0f32, 7.0), Point::new(10.0, 7.0));
+        assert!(circle.intersects_line(&graze));
+        assert_eq!(
+            circle.line_intersections(&graze),
+            vec![Point::new(5.0, 7.0)]
+        );
+        let miss = Line::new(Point::new(0.0f32, 7.5), Point::new(10.0, 7.5));
+        assert!(!circle.intersects_line(&miss));
+        assert!(circle.line_intersections(&miss).is_empty());
+        let short = Line::new(Point::new(0.0f32, 5.0), Point::new(2.5, 5.0));
+        assert!(!circle.intersects_line(&short));
+        let inside = Line::new(Point::new(4.5f32, 5.0), Point::new(5.5, 5.0));
+        assert!(circle.intersects_line(&inside));
+        assert!(circle.line_intersections(&inside).is_empty());
+    }
+
+    #[test]
+    fn closest_point() {
+        let line = Line::new(Point::new(0.0f32, 0.0), Point::new(4.0, 0.0));
+        assert_eq!(
+            line.closest_point(&Point::new(1.5, 3.0)),
+            Point::new(1.5, 0.0)
+        );
+        assert_eq!(line.distance_to_point(&Point::new(1.5, 3.0)), 3.0);
+        assert_eq!(
+            line.closest_point(&Point::new(-2.0, 1.0)),
+            Point::new(0.0, 0.0)
+        );
+        assert_eq!(
+            line.closest_point(&Point::new(7.0, -4.0)),
+            Point::new(4.0, 0.0)
+        );
+        assert_eq!(line.distance_to_point(&Point::new(7.0, -4.0)), 5.0);
+        let diagonal = Line::new(Point::new(0.0f32, 0.0), Point::new(2.0, 2.0));
+        assert_eq!(
+            diagonal.closest_point(&Point::new(0.0, 2.0)),
+            Point::new(1.0, 1.0)
+        );
+        let point = Line::new(Point::new(1.0f32, 1.0), Point::new(1.0, 1.0));
+        assert_eq!(
+            point.closest_point(&Point::new(4.0, 5.0)),
+            Point::new(1.0, 1.0)
+        );
+        assert_eq!(point.distance_to_point(&Point::new(4.0, 5.0)), 5.0);
+    }
+
+    #[test]
+    fn contains_tolerates_rounding() {
+        let wall = Line::new(Point::new(10.0f32, 0.0), Point::new(10.0, 40.0));
+        let border = Line::new(Point::new(10.000001f32, 20.0), Point::new(10.000001, 30.0));
+        assert!(wall.contains(&border));
+        let border = Line::new(
+            Point::new(9.999999f32, 0.0),
+            Point::new(9.999999, 40.000002),
+        );
+        assert!(wall.contains(&border));
+        assert!(!wall.contains(&Line::new(Point::new(10.1, 20.0), Point::new(10.1, 30.0))));
+        assert!(!wall.contains(&Line::new(Point::new(10.0, 30.0), Point::new(10.0, 41.0))));
+    }
+
+    #[test]
+    fn line_approx_eq() {
+        let wall = Line::new(Point::new(10.0f32, 0.0), Point::new(10.0, 40.0));
+        let reversed = Line::new(Point::new(10.000001f32, 40.0), Point::new(10.0, 0.000001));
+        assert!(wall.approx_eq(&reversed, EPSILON));
+        assert!(!wall.approx_eq(&reversed, 1e-9));
+        assert!(!wall.approx_eq(&Line::new(wall.a, Point::new(10.0, 39.0)), EPSILON));
+    }
+
+    #[test]
+    fn aabb() {
+        let bounds = Aabb::from_points(&[
+            Point::new(10.0f32, 40.0),
+            Point::new(30.0, 20.0),
+            Point::new(20.0, 30.0),
+        ]);
+        assert_eq!(bounds.min, Point::new(10.0, 20.0));
+        assert_eq!(bounds.max, Point::new(30.0, 40.0));
+        for p in [
+            bounds.min,
+            bounds.max,
+            Point::new(30.0, 20.0),
+            Point::new(10.0, 40.0),
+            Point::new(20.0, 30.0),
+        ] {
+            assert!(bounds.contains(&p), "{}", p);
+        }
+        assert!(!bounds.contains(&Point::new(9.0, 30.0)));
+        assert!(!Aabb::<f32>::from_points(&[]).contains(&Point::new(0.0, 0.0)));
+
+        // Crossing the box with both ends outside, and passing it by.
+        let crossing = Line::new(Point::new(0.0f32, 30.0), Point::new(50.0, 30.0));
+        let disjoint = Line::new(Point::new(0.0f32, 0.0), Point::new(50.0, 10.0));
+        assert!(bounds.intersects_line(&crossing));
+        assert!(!bounds.intersects_line(&disjoint));
+    }
 }
@@ -2,26 +2,160 @@ use macroquad::prelude::*;
 use macroquad::ui::root_ui;
 use std::collections::VecDeque;
 
+mod background;
+mod bindings;
+mod camera;
+mod clipboard;
+mod config;
+mod error;
+mod export;
 mod game;
+mod gamepad;
 mod geometrie;
 mod menu;
+mod replay;
+mod rng;
+mod scores;
+mod stats;
+mod theme;
 
-use game::Game;
-use menu::{Menus, make_skin};
+use background::{BackgroundStyle, draw_background};
+use bindings::{Action, FULLSCREEN_KEY, KeyBindings, SCREENSHOT_KEY};
+use camera::Camera;
+use clipboard::SystemClipboard;
+use game::{Algorithm, Game, GenerationAnimator, Placement, grid_size};
+use geometrie::{Aabb, Line, Point};
+use menu::{MenuState, Menus, make_skin};
+use replay::Replay;
+use scores::Submission;
+use theme::{THEMES, Theme};
 
 const WINDOW_DIMENSIONS: Vec2 = Vec2 {
     x: 1200f32,
     y: 800f32,
 };
-const GRID_SIZES: [usize; 3] = [100, 50, 25];
+const MIN_DIMENSIONS: Vec2 = Vec2 {
+    x: 400f32,
+    y: 300f32,
+};
+const GRID_SIZES: [(&str, usize); 4] = [
+    ("small", 100),
+    ("medium", 50),
+    ("large", 25),
+    ("extreme", 20),
+];
+const MIN_GRID_SIZE: usize = 10;
+const MAX_GRID_SIZE: usize = 150;
 const SEED: Option<u64> = None;
 const RAYS: usize = 360;
-const RAY_LENGTH: usize = 4;
-const TARGET_THRESHOLD: usize = 3;
-const CIRCLE_SIZE: usize = 5;
+const VISION_RANGE: f32 = 4.0;
+const MIN_VISION_RANGE: f32 = 1.0;
+const CONE_HALF_ANGLE: f32 = 45.0;
+const MIN_CONE_ANGLE: f32 = 10.0;
+const MAX_VISION_RANGE: f32 = 16.0;
+const PLAYER_RADIUS: f32 = 6.0;
+const TARGET_RADIUS: f32 = 6.0;
+const MIN_RADIUS: f32 = 1.0;
 const FONT_SIZE: u16 = 50;
-const TEXT_COLOR: Color = WHITE;
 const DROPOUT: f32 = 0.01;
+const CELL_ASPECT: f32 = 1.0;
+const MIN_CELL_ASPECT: f32 = 0.5;
+const MAX_CELL_ASPECT: f32 = 2.0;
+const ACCELERATION: f32 = 40.0;
+const MAX_SPEED: f32 = 600.0;
+const FRICTION: f32 = 12.0;
+const MOVE_SPEED: f32 = 1.0;
+const MOUSE_SENSITIVITY: f32 = 1.0;
+const KEYBOARD_PULL: f32 = 75.0;
+const GAMEPAD_PULL: f32 = 75.0;
+const STICK_DEAD_ZONE: f32 = 0.15;
+const DEAD_ZONE: f32 = 2.0;
+const NUDGE_STEP: f32 = 2.0;
+const ZOOM: f32 = 2.0;
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 4.0;
+const ZOOM_STEP: f32 = 1.1;
+const CAMERA_SMOOTHING: f32 = 8.0;
+const TIME_SCALE: f32 = 0.5;
+const VIGNETTE_STEPS: usize = 24;
+const FRAME_SAMPLES: usize = 120;
+const WALL_MEMORY_BRIGHTNESS: f32 = 0.25;
+const WALL_MEMORY_FADE: f32 = 1.5;
+const PEEK_DURATION: f32 = 1.5;
+const PEEK_PENALTY: f32 = 5.0;
+const PEEK_COOLDOWN: f32 = 10.0;
+const GATE_COUNT: usize = 8;
+const GATE_PERIOD: f32 = 4.0;
+const WALL_THICKNESS: f32 = 1.0;
+const PROXIMITY_RANGE: f32 = 400.0;
+const PULSE_RATE: f32 = 1.0;
+const TRAIL_LENGTH: usize = 64;
+const TRAIL_FADE: f32 = 0.8;
+const MINIMAP_SCALE: f32 = 0.2;
+const WIN_ANIMATION: f32 = 1.0;
+const WIN_RINGS: usize = 3;
+const WIN_RING_RADIUS: f32 = 150.0;
+const FLOORS: usize = 5;
+const MIN_SEPARATION: f32 = 0.25;
+const MAX_SEPARATION: f32 = 0.5;
+const PLACEMENT_ATTEMPTS: usize = 100;
+const TIME_BUDGETS: [f64; GRID_SIZES.len()] = [30.0, 60.0, 150.0, 240.0];
+const REPLAY_TOLERANCE: f64 = 0.1;
+const PLAYBACK_HOLD: f64 = 1.0;
+const TOAST_DURATION: f64 = 2.5;
+const SETTINGS_SAVE_DELAY: f64 = 1.0;
+const GENERATION_ANIMATION: f64 = 4.0;
+const GENERATION_HOLD: f64 = 2.0;
+const ALGORITHMS: [(&str, Algorithm); 4] = [
+    ("kruskal", Algorithm::Kruskal),
+    ("backtracker", Algorithm::Dfs),
+    ("prim", Algorithm::Prim),
+    ("wilson", Algorithm::Wilson),
+];
+const PLACEMENTS: [(&str, Placement); 2] = [
+    ("random", Placement::Random),
+    ("farthest", Placement::Farthest),
+];
+const LIGHT_RENDERS: [(&str, LightRender); 2] = [
+    ("rays", LightRender::Rays),
+    ("polygon", LightRender::Polygon),
+];
+const VISION_MODES: [(&str, VisionMode); 2] = [
+    ("full", VisionMode::Omnidirectional),
+    (
+        "cone",
+        VisionMode::Cone {
+            half_angle: CONE_HALF_ANGLE,
+        },
+    ),
+];
+const MOVEMENT_MODES: [(&str, MovementMode); 2] = [
+    ("mouse", MovementMode::Mouse),
+    ("keyboard", MovementMode::Keyboard),
+];
+const BACKGROUNDS: [(&str, BackgroundStyle); 3] = [
+    ("flat", BackgroundStyle::Flat),
+    ("stars", BackgroundStyle::Stars),
+    ("gradient", BackgroundStyle::Gradient),
+];
+const RAY_COUNTS: [(&str, usize); 4] = [("90", 90), ("180", 180), ("360", 360), ("720", 720)];
+const FPS_CAPS: [(&str, Option<u32>); 4] = [
+    ("30", Some(30)),
+    ("60", Some(60)),
+    ("120", Some(120)),
+    ("Unlimited", None),
+];
+const DIFFICULTIES: [(&str, Difficulty); 4] = [
+    ("easy", Difficulty::Easy),
+    ("normal", Difficulty::Normal),
+    ("hard", Difficulty::Hard),
+    ("custom", Difficulty::Custom),
+];
+const KEY_COLORS: [Color; 3] = [YELLOW, MAGENTA, ORANGE];
+const TARGET_WANDER_PERIOD: f64 = 2.5;
+const WANDER_SEED: u64 = 0x5eed_7a46_e7ed;
+const TARGET_KEYS: [(&str, usize); 4] = [("0", 0), ("1", 1), ("3", 3), ("5", 5)];
+const BOUNCES: [(&str, usize); 4] = [("off", 0), ("1", 1), ("2", 2), ("3", 3)];
 
 fn window_conf() -> Conf {
     Conf {
@@ -30,7 +164,7 @@ fn window_conf() -> Conf {
         high_dpi: true,
         window_height: WINDOW_DIMENSIONS.y as i32,
         window_width: WINDOW_DIMENSIONS.x as i32,
-        window_resizable: false,
+        window_resizable: true,
         platform: miniquad::conf::Platform {
             linux_backend: miniquad::conf::LinuxBackend::WaylandOnly,
             ..Default::default()
@@ -39,28 +173,320 @@ fn window_conf() -> Conf {
     }
 }
 
-pub struct Settings {
+#[derive(Clone, Copy, PartialEq)]
+pub enum LightRender {
+    Rays,
+    Polygon,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum VisionMode {
+    Omnidirectional,
+    Cone { half_angle: f32 },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl Difficulty {
+    // Labyrinth size index, dropout and target radius.
+    fn preset(&self) -> Option<(usize, f32, f32)> {
+        match self {
+            Difficulty::Easy => Some((0, 0.1, 12.0)),
+            Difficulty::Normal => Some((1, DROPOUT, TARGET_RADIUS)),
+            Difficulty::Hard => Some((2, 0.0, 4.0)),
+            Difficulty::Custom => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MovementMode {
+    Mouse,
+    Keyboard,
+}
+
+#[derive(Clone)]
+pub struct GameSettings {
     pub draw_labyrinth: bool,
+    pub practice: bool,
+    pub campaign: bool,
+    pub countdown: bool,
+    pub floors: usize,
     pub draw_delta_time: bool,
+    pub draw_frame_stats: bool,
+    pub draw_grid_coords: bool,
+    pub theme: usize,
+    pub light_render: LightRender,
+    pub ray_count: usize,
+    pub vision_range: f32,
+    pub vision_mode: VisionMode,
+    pub background: BackgroundStyle,
+    pub fps_cap: Option<u32>,
+    pub wall_thickness: f32,
+    pub difficulty: Difficulty,
     pub labyrinth_size: usize,
+    pub custom_grid_size: Option<usize>,
+    pub seed: Option<u64>,
+    pub algorithm: Algorithm,
     pub dropout: f32,
-    pub target_threshold: usize,
+    pub braid: f32,
+    pub cell_aspect: f32,
+    pub placement: Placement,
+    pub min_separation: f32,
+    pub player_radius: f32,
+    pub target_radius: f32,
+    pub keys_and_doors: bool,
+    pub target_keys: usize,
+    pub bounces: usize,
+    pub wandering_target: bool,
+    pub wall_memory: bool,
+    pub dynamic_walls: bool,
+    pub proximity_cue: bool,
+    pub trail: bool,
+    pub minimap: bool,
+    pub peek_duration: f32,
+    pub peek_penalty: f32,
+    pub peek_cooldown: f32,
+    pub acceleration: f32,
+    pub max_speed: f32,
+    pub friction: f32,
+    pub move_speed: f32,
+    pub movement_mode: MovementMode,
+    pub mouse_sensitivity: f32,
+    pub stick_dead_zone: f32,
+    pub dead_zone: f32,
+    pub nudge_step: f32,
+    pub key_bindings: KeyBindings,
+    pub slow_motion: bool,
+    pub time_scale: f32,
+    pub vignette: f32,
+    pub camera_follow: bool,
+    pub aspect_lock: bool,
+    pub fullscreen: bool,
+    pub zoom: f32,
+    pub win_animation: f32,
+    pub dimensions: Vec2,
 }
 
-impl Settings {
+impl GameSettings {
     pub fn new(
         draw_labyrinth: bool,
         draw_delta_time: bool,
         labyrinth_size: usize,
         dropout: f32,
-        target_threshold: usize,
+        player_radius: f32,
+        target_radius: f32,
     ) -> Self {
         Self {
             draw_labyrinth,
+            practice: false,
+            campaign: false,
+            countdown: false,
+            floors: FLOORS,
             draw_delta_time,
+            draw_frame_stats: false,
+            draw_grid_coords: false,
+            theme: 0,
+            light_render: LightRender::Rays,
+            ray_count: RAYS,
+            vision_range: VISION_RANGE,
+            vision_mode: VisionMode::Omnidirectional,
+            background: BackgroundStyle::Flat,
+            fps_cap: None,
+            wall_thickness: WALL_THICKNESS,
+            difficulty: Difficulty::Normal,
             labyrinth_size,
-            dropout,
-            target_threshold,
+            custom_grid_size: None,
+            seed: SEED,
+            algorithm: Algorithm::Kruskal,
+            dropout: dropout.clamp(0.0, 1.0),
+            braid: 0.0,
+            cell_aspect: CELL_ASPECT,
+            placement: Placement::Random,
+            min_separation: MIN_SEPARATION,
+            player_radius,
+            target_radius,
+            keys_and_doors: false,
+            target_keys: 0,
+            bounces: 0,
+            wandering_target: false,
+            wall_memory: true,
+            dynamic_walls: false,
+            proximity_cue: true,
+            trail: false,
+            minimap: false,
+            peek_duration: PEEK_DURATION,
+            peek_penalty: PEEK_PENALTY,
+            peek_cooldown: PEEK_COOLDOWN,
+            acceleration: ACCELERATION,
+            max_speed: MAX_SPEED,
+            friction: FRICTION,
+            move_speed: MOVE_SPEED,
+            movement_mode: MovementMode::Mouse,
+            mouse_sensitivity: MOUSE_SENSITIVITY,
+            stick_dead_zone: STICK_DEAD_ZONE,
+            dead_zone: DEAD_ZONE,
+            nudge_step: NUDGE_STEP,
+            key_bindings: KeyBindings::new(),
+            slow_motion: false,
+            time_scale: TIME_SCALE,
+            vignette: 0.0,
+            camera_follow: false,
+            aspect_lock: true,
+            fullscreen: false,
+            zoom: ZOOM,
+            win_animation: WIN_ANIMATION,
+            dimensions: WINDOW_DIMENSIONS,
+        }
+    }
+
+    pub fn cell_size(&self) -> Point<usize> {
+        let grid_size = match self.custom_grid_size {
+            Some(size) => size.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE),
+            None => GRID_SIZES[self.labyrinth_size].1,
+        } as f32;
+        let stretch = self.cell_aspect.sqrt();
+        // Leftover pixels are centered by the grid offset, but every maze keeps two cells a side.
+        Point::new(
+            ((grid_size * stretch).round() as usize).clamp(1, self.dimensions.x as usize / 2),
+            ((grid_size / stretch).round() as usize).clamp(1, self.dimensions.y as usize / 2),
+        )
+    }
+
+    pub fn for_floor(&self, floor: usize) -> Self {
+        Self {
+            labyrinth_size: (self.labyrinth_size + floor - 1).min(GRID_SIZES.len() - 1),
+            ..self.clone()
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &THEMES[self.theme].1
+    }
+
+    pub fn share_code(&self, seed: u64) -> String {
+        let algorithm = ALGORITHMS
+            .iter()
+            .position(|(_, algorithm)| *algorithm == self.algorithm)
+            .unwrap_or(0);
+        let size = match self.custom_grid_size {
+            Some(size) => format!("c{}", size),
+            None => self.labyrinth_size.to_string(),
+        };
+        let mut code = format!(
+            "{}:{}:{}:{}:{}",
+            seed, size, self.dropout, algorithm, self.braid
+        );
+        // Only mazes off the default window size carry their dimensions.
+        if self.dimensions != WINDOW_DIMENSIONS {
+            code += &format!(":{}x{}", self.dimensions.x, self.dimensions.y);
+        }
+        // Likewise the other generation options, each tagged by a letter.
+        if self.cell_aspect != CELL_ASPECT {
+            code += &format!(":a{}", self.cell_aspect);
+        }
+        if self.placement != Placement::Random {
+            let placement = PLACEMENTS
+                .iter()
+                .position(|(_, placement)| *placement == self.placement)
+                .unwrap_or(0);
+            code += &format!(":p{}", placement);
+        }
+        if self.min_separation != MIN_SEPARATION {
+            code += &format!(":s{}", self.min_separation);
+        }
+        if self.keys_and_doors {
+            code += ":k";
+        }
+        if self.target_keys > 0 {
+            code += &format!(":t{}", self.target_keys);
+        }
+        if self.dynamic_walls {
+            code += ":g";
+        }
+        code
+    }
+
+    pub fn apply_share_code(&mut self, code: &str) -> Option<u64> {
+        let mut parts = code.trim().split(':');
+        let seed = parts.next()?.parse().ok()?;
+        let size = parts.next()?;
+        let (labyrinth_size, custom_grid_size) = match size.strip_prefix('c') {
+            Some(size) => (self.labyrinth_size, Some(size.parse().ok()?)),
+            None => (size.parse().ok()?, None),
+        };
+        let dropout = parts.next()?.parse().ok()?;
+        // Older codes stop before the algorithm or the braid part.
+        let algorithm = match parts.next() {
+            Some(part) => part.parse().ok().filter(|i| *i < ALGORITHMS.len())?,
+            None => 0,
+        };
+        let braid = match parts.next() {
+            Some(part) => part.parse().ok()?,
+            None => 0.0,
+        };
+        // Whatever follows is optional, a missing part means the default.
+        let mut dimensions = WINDOW_DIMENSIONS;
+        let mut cell_aspect = CELL_ASPECT;
+        let mut placement = Placement::Random;
+        let mut min_separation = MIN_SEPARATION;
+        let mut keys_and_doors = false;
+        let mut target_keys = 0;
+        let mut dynamic_walls = false;
+        for part in parts {
+            match part.split_at_checked(1)? {
+                ("a", aspect) => cell_aspect = aspect.parse().ok()?,
+                ("k", "") => keys_and_doors = true,
+                ("g", "") => dynamic_walls = true,
+                ("t", count) => target_keys = count.parse().ok()?,
+                ("s", separation) => min_separation = separation.parse().ok()?,
+                ("p", index) => placement = PLACEMENTS.get(index.parse::<usize>().ok()?)?.1,
+                _ => {
+                    let (width, height) = part.split_once('x')?;
+                    dimensions = vec2(width.parse().ok()?, height.parse().ok()?);
+                }
+            }
+        }
+        if labyrinth_size >= GRID_SIZES.len()
+            || custom_grid_size.is_some_and(|size| !(MIN_GRID_SIZE..=MAX_GRID_SIZE).contains(&size))
+            || !(0.0..=1.0).contains(&dropout)
+            || !(0.0..=1.0).contains(&braid)
+            || !dimensions.is_finite()
+            || dimensions.cmplt(MIN_DIMENSIONS).any()
+            || !(MIN_CELL_ASPECT..=MAX_CELL_ASPECT).contains(&cell_aspect)
+            || !(0.0..=MAX_SEPARATION).contains(&min_separation)
+            || !TARGET_KEYS.iter().any(|(_, count)| *count == target_keys)
+        {
+            return None;
+        }
+        self.difficulty = Difficulty::Custom;
+        self.labyrinth_size = labyrinth_size;
+        self.custom_grid_size = custom_grid_size;
+        self.dropout = dropout;
+        self.algorithm = ALGORITHMS[algorithm].1;
+        self.braid = braid;
+        self.dimensions = dimensions;
+        self.cell_aspect = cell_aspect;
+        self.placement = placement;
+        self.min_separation = min_separation;
+        self.keys_and_doors = keys_and_doors;
+        self.target_keys = target_keys;
+        self.dynamic_walls = dynamic_walls;
+        Some(seed)
+    }
+
+    pub fn apply_difficulty(&mut self) {
+        if let Some((labyrinth_size, dropout, target_radius)) = self.difficulty.preset() {
+            self.labyrinth_size = labyrinth_size;
+            self.custom_grid_size = None;
+            self.dropout = dropout;
+            self.target_radius = target_radius;
         }
     }
 }
@@ -70,6 +496,8 @@ enum GameState {
     Playing,
     Paused,
     Won,
+    Lost,
+    Watching,
 }
 
 #[macroquad::main(window_conf)]
@@ -83,163 +511,970 @@ async fn main() {
     };
     rand::srand(seed);
 
-    let mut settings = Settings::new(false, true, 1, DROPOUT, TARGET_THRESHOLD);
-    let mut game = Game::new(
-        GRID_SIZES[settings.labyrinth_size],
-        settings.dropout,
-        settings.target_threshold,
-    );
+    let mut settings = GameSettings::load();
+    let mut saved_settings = settings.to_toml();
+    let mut pending_settings = (saved_settings.clone(), get_time());
+    if settings.fullscreen {
+        set_fullscreen(true);
+    }
+    settings.dimensions = screen_size().max(MIN_DIMENSIONS);
+    let mut game = Game::new(&settings);
     let mut game_state = GameState::MainMenu;
+    let mut animator = GenerationAnimator::new(&settings);
+    let mut camera = Camera::new(&settings);
 
-    let mut frame_durations = DeltaTime::new();
+    let mut frame_durations = DeltaTime::new(FRAME_SAMPLES);
+    let mut toast: Option<(String, f64)> = None;
 
     let mut display_new_game_menu = true;
-    let mut display_options_menu = false;
+    let mut menu_state = MenuState::new(Box::new(SystemClipboard), &settings);
+    let mut gamepad = gamepad::connect();
 
     loop {
         let start_time = macroquad::miniquad::date::now();
-        clear_background(BLACK);
+        gamepad.poll();
+        menu_state.focus.navigate(gamepad.navigation());
+        clear_background(match settings.aspect_lock {
+            true => BLACK,
+            false => settings.theme().background,
+        });
+
+        // Outside of a run the maze follows the window, regenerating once a cell row or column
+        // more or less fits.
+        let dimensions = screen_size().max(MIN_DIMENSIONS);
+        if matches!(game_state, GameState::MainMenu) && dimensions != settings.dimensions {
+            let cell_size = settings.cell_size();
+            let regenerate =
+                grid_size(dimensions, cell_size) != grid_size(settings.dimensions, cell_size);
+            settings.dimensions = dimensions;
+            if regenerate {
+                game = Game::new(&settings);
+                animator = GenerationAnimator::new(&settings);
+            }
+        }
 
         match game_state {
             GameState::MainMenu => {
-                draw_labyrinth(&game);
-                if display_options_menu {
-                    Menus::Options.display(
+                animator.update(&settings);
+                camera.update(&settings, None);
+                camera.enable(settings.theme().background);
+                draw_background(
+                    settings.background,
+                    settings.theme().background,
+                    settings.dimensions,
+                    get_time(),
+                );
+                draw_generation(&animator, &settings);
+                camera.disable();
+                if menu_state.display_leaderboard_menu {
+                    Menus::Leaderboard.display(
                         &mut game,
                         &mut game_state,
                         &mut settings,
-                        &mut display_options_menu,
+                        &mut menu_state,
                     );
-                } else {
-                    Menus::Main.display(
+                } else if menu_state.display_bindings_menu {
+                    Menus::Bindings.display(
+                        &mut game,
+                        &mut game_state,
+                        &mut settings,
+                        &mut menu_state,
+                    );
+                } else if menu_state.display_options_menu {
+                    Menus::Options.display(
                         &mut game,
                         &mut game_state,
                         &mut settings,
-                        &mut display_options_menu,
+                        &mut menu_state,
                     );
+                } else {
+                    Menus::Main.display(&mut game, &mut game_state, &mut settings, &mut menu_state);
                 }
             }
             GameState::Playing => {
-                if settings.draw_labyrinth {
-                    draw_labyrinth(&game);
+                camera.update(&settings, Some(game.position));
+                camera.enable(settings.theme().background);
+                draw_background(
+                    settings.background,
+                    settings.theme().background,
+                    settings.dimensions,
+                    get_time(),
+                );
+                if settings.draw_labyrinth || game.timer.practice {
+                    draw_labyrinth(&game, &settings, &camera.view(), 1.0);
+                } else if settings.wall_memory {
+                    draw_wall_memory(&game, &settings);
+                }
+                if game.peek_timer > 0.0 {
+                    draw_labyrinth(
+                        &game,
+                        &settings,
+                        &camera.view(),
+                        game.peek_timer / settings.peek_duration,
+                    );
+                }
+                game.update_position(
+                    &settings,
+                    camera.to_world(mouse_position().into()),
+                    gamepad.left_stick(),
+                );
+                if settings.trail {
+                    draw_trail(&game, settings.theme());
+                }
+                draw_keys_and_doors(&game);
+                if game.floors == 1
+                    && let Some((_, ghost)) =
+                        menu_state.scores.best(&settings.share_code(game.seed))
+                {
+                    draw_ghost(&game, ghost, settings.theme());
                 }
-                game.update_position();
-                draw_player(&game);
-                draw_time(&game);
+                draw_player(&game, &settings);
+                if settings.draw_grid_coords {
+                    draw_grid_coords(&game, &settings);
+                }
+                camera.disable();
+                draw_vignette(settings.vignette);
+                if settings.minimap {
+                    draw_minimap(&game, settings.theme());
+                }
+                draw_time(&game, &settings);
 
-                if game.found_target() {
-                    game.timer.stop();
+                if game.timer.expired() {
+                    if let Err(e) = game.timer.stop() {
+                        error!("{}", e);
+                    }
+                    menu_state.stats.quit(game.timer.elapsed());
+                    game_state = GameState::Lost;
+                } else if game.found_target() && !game.is_last_floor() {
+                    game.descend(&settings);
+                } else if game.found_target() {
+                    if let Err(e) = game.timer.stop() {
+                        error!("{}", e);
+                    }
+                    let maze = settings.share_code(game.seed);
+                    let had_ghost = menu_state.scores.best(&maze).is_some();
+                    if game.floors == 1
+                        && let Err(e) = game.replay.save(&maze)
+                    {
+                        error!("Could not save replay: {}", e);
+                    }
+                    let submission = match game.floors {
+                        1 => menu_state.scores.submit(
+                            maze,
+                            &settings,
+                            &game.timer,
+                            game.replay.clone(),
+                        ),
+                        _ => Submission::default(),
+                    };
+                    menu_state.beat_ghost = had_ghost && submission.new_best;
+                    menu_state.rank = submission.rank;
+                    if (submission.rank.is_some() || submission.new_best)
+                        && let Err(e) = menu_state.scores.save()
+                    {
+                        error!("Could not save scores: {}", e);
+                    }
+                    if let Some(result) = game.timer.result {
+                        menu_state.stats.win(result);
+                    }
                     game_state = GameState::Won;
+                    game.win_animation = settings.win_animation;
                     display_new_game_menu = true;
                 }
 
-                if is_key_pressed(KeyCode::Escape) {
+                if settings.key_bindings.pressed(Action::Peek) {
+                    game.peek(&settings);
+                }
+
+                if settings.key_bindings.pressed(Action::Hint) {
+                    game.hint = !game.hint;
+                }
+
+                let (_, wheel) = mouse_wheel();
+                if settings.camera_follow && wheel != 0.0 {
+                    settings.zoom =
+                        (settings.zoom * ZOOM_STEP.powf(wheel.signum())).clamp(MIN_ZOOM, MAX_ZOOM);
+                }
+
+                if settings.key_bindings.pressed(Action::Export) {
+                    for export in [
+                        export::export_png(&game, &settings, true),
+                        export::export_text(&game),
+                    ] {
+                        match export {
+                            Ok(path) => info!("Exported maze to {}", path.display()),
+                            Err(e) => error!("Could not export maze: {}", e),
+                        }
+                    }
+                }
+
+                if settings.key_bindings.pressed(Action::Import) {
+                    match export::import_text(&settings) {
+                        Ok(imported) => {
+                            game = imported;
+                            if let Err(e) = game.timer.start() {
+                                error!("{}", e);
+                            }
+                        }
+                        Err(e) => error!("Could not import maze: {}", e),
+                    }
+                }
+
+                if settings.key_bindings.pressed(Action::SlowMotion) {
+                    settings.slow_motion = !settings.slow_motion;
+                }
+
+                if settings.key_bindings.pressed(Action::Pause) {
                     game_state = GameState::Paused;
-                    game.timer.pause();
+                    if let Err(e) = game.timer.pause() {
+                        error!("{}", e);
+                    }
                 }
             }
             GameState::Paused => {
-                if settings.draw_labyrinth {
-                    draw_labyrinth(&game);
+                camera.update(&settings, Some(game.position));
+                camera.enable(settings.theme().background);
+                draw_background(
+                    settings.background,
+                    settings.theme().background,
+                    settings.dimensions,
+                    get_time(),
+                );
+                if settings.draw_labyrinth || game.timer.practice {
+                    draw_labyrinth(&game, &settings, &camera.view(), 1.0);
+                } else if settings.wall_memory {
+                    draw_wall_memory(&game, &settings);
+                }
+                if game.peek_timer > 0.0 {
+                    draw_labyrinth(
+                        &game,
+                        &settings,
+                        &camera.view(),
+                        game.peek_timer / settings.peek_duration,
+                    );
+                }
+                draw_keys_and_doors(&game);
+                draw_player(&game, &settings);
+                camera.disable();
+                draw_vignette(settings.vignette);
+                if settings.draw_grid_coords {
+                    draw_maze_stats(&game, &settings);
                 }
-                draw_player(&game);
-                draw_time(&game);
-                if is_key_pressed(KeyCode::Escape) {
+                draw_time(&game, &settings);
+                if settings.key_bindings.pressed(Action::Pause) {
                     game_state = GameState::Playing;
-                    game.timer.resume();
+                    if let Err(e) = game.timer.resume() {
+                        error!("{}", e);
+                    }
+                    menu_state.confirm = None;
                 }
-                Menus::Pause.display(
-                    &mut game,
-                    &mut game_state,
-                    &mut settings,
-                    &mut display_options_menu,
-                );
+                Menus::Pause.display(&mut game, &mut game_state, &mut settings, &mut menu_state);
             }
             GameState::Won => {
-                if is_key_pressed(KeyCode::Escape) {
-                    display_new_game_menu = !display_new_game_menu;
+                camera.fit(&settings, &game.bounds());
+                camera.enable(settings.theme().background);
+                draw_background(
+                    settings.background,
+                    settings.theme().background,
+                    settings.dimensions,
+                    get_time(),
+                );
+                if game.win_animation > 0.0 {
+                    if get_last_key_pressed().is_some()
+                        || is_mouse_button_pressed(MouseButton::Left)
+                    {
+                        game.win_animation = 0.0;
+                    }
+                } else {
+                    if settings.key_bindings.pressed(Action::Pause) {
+                        display_new_game_menu = !display_new_game_menu;
+                    }
+                    if display_new_game_menu {
+                        Menus::GameOver.display(
+                            &mut game,
+                            &mut game_state,
+                            &mut settings,
+                            &mut menu_state,
+                        );
+                    }
                 }
-                if display_new_game_menu {
-                    Menus::GameOver.display(
-                        &mut game,
-                        &mut game_state,
-                        &mut settings,
-                        &mut display_options_menu,
-                    );
+                let reveal = match game.win_animation > 0.0 {
+                    true => 1.0 - game.win_animation / settings.win_animation,
+                    false => 1.0,
+                };
+                draw_labyrinth(&game, &settings, &camera.view(), reveal);
+                draw_path(&game, settings.theme(), reveal);
+                game.update_position(
+                    &settings,
+                    camera.to_world(mouse_position().into()),
+                    gamepad.left_stick(),
+                );
+                draw_keys_and_doors(&game);
+                draw_player(&game, &settings);
+                if game.win_animation > 0.0 {
+                    draw_win_animation(&game, &settings);
+                }
+                camera.disable();
+                draw_vignette(settings.vignette);
+                draw_time(&game, &settings);
+            }
+            GameState::Lost => {
+                camera.update(&settings, Some(game.position));
+                camera.enable(settings.theme().background);
+                draw_background(
+                    settings.background,
+                    settings.theme().background,
+                    settings.dimensions,
+                    get_time(),
+                );
+                draw_labyrinth(&game, &settings, &camera.view(), 1.0);
+                draw_path(&game, settings.theme(), 1.0);
+                draw_keys_and_doors(&game);
+                draw_player(&game, &settings);
+                camera.disable();
+                draw_vignette(settings.vignette);
+                draw_time(&game, &settings);
+                Menus::Lost.display(&mut game, &mut game_state, &mut settings, &mut menu_state);
+            }
+            GameState::Watching => {
+                // A loaded replay brings the settings of its own maze.
+                let settings = menu_state
+                    .playback
+                    .as_ref()
+                    .map_or(&settings, |playback| &playback.settings);
+                camera.update(settings, Some(game.position));
+                camera.enable(settings.theme().background);
+                draw_background(
+                    settings.background,
+                    settings.theme().background,
+                    settings.dimensions,
+                    get_time(),
+                );
+                if settings.draw_labyrinth || game.timer.practice {
+                    draw_labyrinth(&game, settings, &camera.view(), 1.0);
+                } else if settings.wall_memory {
+                    draw_wall_memory(&game, settings);
+                }
+                if let Some(playback) = &menu_state.playback
+                    && let Some(position) = playback.position()
+                {
+                    game.play_back(settings, playback.time(), position);
+                }
+                if settings.trail {
+                    draw_trail(&game, settings.theme());
+                }
+                draw_keys_and_doors(&game);
+                draw_player(&game, settings);
+                camera.disable();
+                draw_vignette(settings.vignette);
+                draw_time(&game, settings);
+
+                let finished = menu_state
+                    .playback
+                    .as_ref()
+                    .is_none_or(|playback| playback.finished());
+                if finished || settings.key_bindings.pressed(Action::Pause) {
+                    menu_state.playback = None;
+                    game_state = match game.timer.result {
+                        Some(_) => GameState::Won,
+                        None => GameState::MainMenu,
+                    };
                 }
-                draw_labyrinth(&game);
-                game.update_position();
-                draw_player(&game);
-                draw_time(&game);
             }
         }
 
-        frame_durations.push(macroquad::miniquad::date::now() - start_time);
+        let calculation_time = macroquad::miniquad::date::now() - start_time;
+        frame_durations.push(calculation_time);
         if settings.draw_delta_time {
             let delta_time = frame_durations.delta_time().unwrap_or(0.0) * 1000.0;
+            let dt_text = match settings.draw_frame_stats {
+                true => format!(
+                    "dt {:.3}ms min {:.3}ms max {:.3}ms p99 {:.3}ms",
+                    delta_time,
+                    frame_durations.min().unwrap_or(0.0) * 1000.0,
+                    frame_durations.max().unwrap_or(0.0) * 1000.0,
+                    frame_durations.percentile(99.0).unwrap_or(0.0) * 1000.0,
+                ),
+                false => format!("dt {:.3}ms", delta_time),
+            };
             draw_text(
-                format!("dt {:.3}ms", delta_time).as_str(),
+                dt_text.as_str(),
                 5.0,
                 FONT_SIZE as f32 * 0.5,
                 FONT_SIZE as f32 * 0.5,
-                TEXT_COLOR,
+                settings.theme().text,
             );
         };
 
+        if is_key_pressed(FULLSCREEN_KEY) {
+            settings.fullscreen = !settings.fullscreen;
+            set_fullscreen(settings.fullscreen);
+        }
+
+        // Taken before the toast is drawn, so a message never ends up in the next shot.
+        if is_key_pressed(SCREENSHOT_KEY) {
+            let seed = match game_state {
+                GameState::MainMenu => None,
+                _ => Some(game.seed),
+            };
+            let message = match export::screenshot(seed) {
+                Ok(path) => format!("Saved {}", path.display()),
+                Err(e) => format!("Screenshot failed: {}", e),
+            };
+            toast = Some((message, get_time() + TOAST_DURATION));
+        }
+        if let Some((message, until)) = &toast {
+            match get_time() < *until {
+                true => draw_toast(message, settings.theme()),
+                false => toast = None,
+            }
+        }
+
+        // A dragged slider changes the settings every frame, so only save once they settle.
+        let current_settings = settings.to_toml();
+        if current_settings != pending_settings.0 {
+            pending_settings = (current_settings, get_time());
+        } else if current_settings != saved_settings
+            && get_time() - pending_settings.1 >= SETTINGS_SAVE_DELAY
+        {
+            if let Err(e) = settings.save() {
+                error!("Could not save settings: {}", e);
+            }
+            saved_settings = current_settings;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(fps) = settings.fps_cap {
+            let remaining = 1.0 / fps as f64 - calculation_time;
+            if remaining > 0.0 {
+                std::thread::sleep(std::time::Duration::from_secs_f64(remaining));
+            }
+        }
+
         next_frame().await
     }
 }
 
-fn draw_player(game: &Game) {
-    let radius = (game.grid_size / CIRCLE_SIZE) as f32;
-    game.get_rays()
+fn draw_player(game: &Game, settings: &GameSettings) {
+    let theme = settings.theme();
+    match settings.light_render {
+        LightRender::Rays => game
+            .get_ray_paths()
+            .for_each(|path| draw_polyline(&path, theme.rays)),
+        LightRender::Polygon => {
+            draw_light_polygon(game, settings);
+            // Reflections leave the lit area, so they are drawn as plain rays on top of it.
+            if settings.bounces > 0 {
+                game.get_ray_paths()
+                    .for_each(|path| draw_polyline(&path[1..], theme.rays));
+            }
+        }
+    }
+    if game.hint {
+        let path = [game.position]
+            .into_iter()
+            .chain(game.solution().iter().copied())
+            .collect::<Vec<_>>();
+        path.windows(2).for_each(|step| {
+            draw_line(step[0].x, step[0].y, step[1].x, step[1].y, 2.0, theme.hint)
+        });
+    }
+    if settings.proximity_cue {
+        let closeness = (1.0 - game.position.distance(&game.target) / PROXIMITY_RANGE).max(0.0);
+        let rate = PULSE_RATE * (1.0 + 3.0 * closeness);
+        let pulse = 0.5 + 0.5 * (get_time() as f32 * rate * std::f32::consts::TAU).sin();
+        draw_circle(
+            game.target.x,
+            game.target.y,
+            game.target_radius * (1.0 + closeness * pulse),
+            theme.target.with_alpha(0.5 * closeness * pulse),
+        );
+    }
+    game.target_keys
+        .iter()
+        .for_each(|key| draw_poly(key.x, key.y, 5, game.target_radius, 0.0, theme.target));
+    draw_circle(
+        game.target.x,
+        game.target.y,
+        game.target_radius,
+        theme.target,
+    );
+    draw_circle(
+        game.position.x,
+        game.position.y,
+        game.player_radius,
+        theme.player,
+    );
+}
+
+fn draw_polyline(points: &[Point<f32>], color: Color) {
+    points
+        .windows(2)
+        .for_each(|line| draw_line(line[0].x, line[0].y, line[1].x, line[1].y, 1.0, color));
+}
+
+fn draw_light_polygon(game: &Game, settings: &GameSettings) {
+    let rays = game.get_rays();
+    if rays.is_empty() {
+        return;
+    }
+    let color = settings.theme().rays.with_alpha(0.3);
+    let vertices = [game.position]
+        .iter()
+        .chain(rays)
+        .map(|p| Vertex::new(p.x, p.y, 0.0, 0.0, 0.0, color))
+        .collect();
+    // Fan around the player, wrapping the last ray back to the first to close the seam.
+    let fans = match settings.vision_mode {
+        VisionMode::Omnidirectional => rays.len(),
+        VisionMode::Cone { .. } => rays.len() - 1,
+    };
+    let indices = (0..fans as u16)
+        .flat_map(|i| [0, i + 1, (i + 1) % rays.len() as u16 + 1])
+        .collect();
+    draw_mesh(&Mesh {
+        vertices,
+        indices,
+        texture: None,
+    });
+}
+
+fn draw_path(game: &Game, theme: &Theme, alpha: f32) {
+    let path = [game.spawn]
+        .into_iter()
+        .chain(game.replay.positions())
+        .collect::<Vec<_>>();
+    // Fades from the player color at the start to the target color at the finish.
+    let steps = path.len().saturating_sub(1).max(1) as f32;
+    path.windows(2).enumerate().for_each(|(i, step)| {
+        let t = i as f32 / steps;
+        let (from, to) = (theme.player, theme.target);
+        let color = Color::new(
+            from.r + (to.r - from.r) * t,
+            from.g + (to.g - from.g) * t,
+            from.b + (to.b - from.b) * t,
+            0.6 * alpha,
+        );
+        draw_line(step[0].x, step[0].y, step[1].x, step[1].y, 2.0, color);
+    });
+}
+
+fn draw_trail(game: &Game, theme: &Theme) {
+    let length = game.trail.len() as f32;
+    game.trail.iter().enumerate().for_each(|(i, p)| {
+        let age = 1.0 - (i + 1) as f32 / length;
+        draw_circle(
+            p.x,
+            p.y,
+            game.player_radius * 0.4,
+            theme.player.with_alpha(0.6 * (1.0 - age * TRAIL_FADE)),
+        );
+    });
+}
+
+fn draw_ghost(game: &Game, ghost: &Replay, theme: &Theme) {
+    if let Some(p) = ghost.position_at(game.timer.elapsed()) {
+        draw_circle(p.x, p.y, game.player_radius, theme.player.with_alpha(0.3));
+    }
+}
+
+fn draw_keys_and_doors(game: &Game) {
+    game.doors
         .iter()
-        .for_each(|ray| draw_line(game.position.x, game.position.y, ray.x, ray.y, 1.0, GREEN));
-    draw_circle(game.target.x, game.target.y, radius, RED);
-    draw_circle(game.position.x, game.position.y, radius, WHITE);
-}
-
-fn draw_labyrinth(game: &Game) {
-    game.walls.iter().for_each(|line| {
-        draw_line(
-            line.a.x.max(1.0),
-            line.a.y.max(1.0),
-            line.b.x.max(1.0),
-            line.b.y.max(1.0),
-            1.0,
-            BLUE,
+        .filter(|door| door.locked)
+        .for_each(|door| {
+            draw_line(
+                door.edge.a.x,
+                door.edge.a.y,
+                door.edge.b.x,
+                door.edge.b.y,
+                3.0,
+                door.color,
+            );
+            let lock = door.edge.midpoint();
+            draw_circle(lock.x, lock.y, door.edge.length() * 0.1, door.color);
+        });
+    game.keys
+        .iter()
+        .filter(|key| !key.collected)
+        .for_each(|key| {
+            draw_poly(
+                key.position.x,
+                key.position.y,
+                4,
+                game.target_radius,
+                45.0,
+                key.color,
+            )
+        });
+}
+
+fn draw_wall(a: Point<f32>, b: Point<f32>, thickness: f32, color: Color) {
+    draw_line(a.x, a.y, b.x, b.y, thickness, color);
+    // Square caps so that thick walls join cleanly at corners.
+    [a, b].iter().for_each(|p| {
+        draw_rectangle(
+            p.x - thickness * 0.5,
+            p.y - thickness * 0.5,
+            thickness,
+            thickness,
+            color,
+        )
+    });
+}
+
+// Walls outside of `view` are skipped, which matters once the camera zooms in.
+fn draw_labyrinth(game: &Game, settings: &GameSettings, view: &Aabb<f32>, alpha: f32) {
+    let color = settings.theme().walls.with_alpha(alpha);
+    game.walls
+        .iter()
+        .filter(|line| view.intersects_line(line))
+        .for_each(|line| {
+            draw_wall(
+                Point::new(line.a.x.max(1.0), line.a.y.max(1.0)),
+                Point::new(line.b.x.max(1.0), line.b.y.max(1.0)),
+                settings.wall_thickness,
+                color,
+            );
+        });
+    game.gates
+        .iter()
+        .filter(|gate| gate.closed)
+        .for_each(|gate| draw_wall(gate.edge.a, gate.edge.b, settings.wall_thickness, color));
+}
+
+fn draw_generation(animator: &GenerationAnimator, settings: &GameSettings) {
+    animator.walls().for_each(|line| {
+        draw_wall(
+            Point::new(line.a.x.max(1.0), line.a.y.max(1.0)),
+            Point::new(line.b.x.max(1.0), line.b.y.max(1.0)),
+            settings.wall_thickness,
+            settings.theme().walls,
+        );
+    });
+}
+
+fn draw_wall_memory(game: &Game, settings: &GameSettings) {
+    game.wall_memory.iter().for_each(|(edge, brightness)| {
+        let edge = Line::<f32>::from(edge.scale(game.cell_size)).translate(game.offset);
+        draw_wall(
+            Point::new(edge.a.x.max(1.0), edge.a.y.max(1.0)),
+            Point::new(edge.b.x.max(1.0), edge.b.y.max(1.0)),
+            settings.wall_thickness,
+            settings.theme().walls.with_alpha(*brightness),
+        );
+    });
+}
+
+fn draw_grid_coords(game: &Game, settings: &GameSettings) {
+    game.nearby_cells().iter().for_each(|cell| {
+        cell.walls.iter().for_each(|wall| {
+            draw_wall(wall.a, wall.b, settings.wall_thickness + 2.0, RED);
+        });
+        let text = format!("({}, {})", cell.position.x, cell.position.y);
+        let text_center = get_text_center(&text, None, FONT_SIZE / 3, 1., 0.);
+        draw_text(
+            &text,
+            cell.center.x - text_center.x,
+            cell.center.y - text_center.y,
+            FONT_SIZE as f32 / 3.0,
+            settings.theme().text,
         );
     });
 }
 
-fn draw_time(game: &Game) {
-    let timer_text = format!("{:.2?}s", game.timer.current());
-    let text_center = get_text_center(&timer_text, None, FONT_SIZE / 2, 1., 0.);
+fn draw_minimap(game: &Game, theme: &Theme) {
+    let size = game.dimensions * MINIMAP_SCALE;
+    let origin = screen_size() - size - vec2(10., 10.);
+    let to_map = |p: Point<f32>| origin + vec2(p.x, p.y) * MINIMAP_SCALE;
+    draw_rectangle(origin.x, origin.y, size.x, size.y, BLACK.with_alpha(0.7));
+    draw_rectangle_lines(origin.x, origin.y, size.x, size.y, 1.0, theme.walls);
+    let cell = vec2(game.cell_size.x as f32, game.cell_size.y as f32) * MINIMAP_SCALE;
+    game.visited_cells().iter().for_each(|visited| {
+        let center = to_map(visited.center);
+        draw_rectangle(
+            center.x - cell.x * 0.5,
+            center.y - cell.y * 0.5,
+            cell.x,
+            cell.y,
+            theme.rays.with_alpha(0.2),
+        );
+        visited.walls.iter().for_each(|wall| {
+            let (a, b) = (to_map(wall.a), to_map(wall.b));
+            draw_line(a.x, a.y, b.x, b.y, 1.0, theme.walls);
+        });
+    });
+    if game.target_discovered() {
+        let target = to_map(game.target);
+        draw_circle(target.x, target.y, 2.0, theme.target);
+    }
+    let player = to_map(game.position);
+    draw_circle(player.x, player.y, 2.0, theme.player);
+}
+
+fn draw_win_animation(game: &Game, settings: &GameSettings) {
+    let progress = 1.0 - game.win_animation / settings.win_animation;
+    (0..WIN_RINGS).for_each(|i| {
+        let ring = (progress - i as f32 / WIN_RINGS as f32).max(0.0);
+        draw_circle_lines(
+            game.target.x,
+            game.target.y,
+            game.target_radius + ring * WIN_RING_RADIUS,
+            3.0,
+            settings.theme().target.with_alpha(1.0 - ring),
+        );
+    });
+}
+
+fn draw_maze_stats(game: &Game, settings: &GameSettings) {
+    let stats = &game.stats;
+    [
+        format!("cells {}", stats.cells),
+        format!("walls {}", stats.walls),
+        format!("passages {}", stats.passages),
+        format!("dead ends {}", stats.dead_ends),
+        format!("diameter {}", stats.diameter),
+        match stats.solution_length {
+            Some(length) => format!("solution {}", length),
+            None => "solution -".to_owned(),
+        },
+        match game.nearest_wall() {
+            Some(distance) => format!("wall {:.1}", distance),
+            None => "wall -".to_owned(),
+        },
+    ]
+    .iter()
+    .enumerate()
+    .for_each(|(i, text)| {
+        draw_text(
+            text,
+            5.0,
+            FONT_SIZE as f32 * 0.5 * (i + 1) as f32,
+            FONT_SIZE as f32 * 0.5,
+            settings.theme().text,
+        );
+    });
+}
+
+fn draw_vignette(intensity: f32) {
+    if intensity <= 0.0 {
+        return;
+    }
+    let band = screen_width().min(screen_height()) * 0.5 / VIGNETTE_STEPS as f32;
+    (0..VIGNETTE_STEPS).for_each(|i| {
+        let inset = i as f32 * band;
+        let falloff = 1.0 - i as f32 / VIGNETTE_STEPS as f32;
+        draw_rectangle_lines(
+            inset,
+            inset,
+            screen_width() - 2.0 * inset,
+            screen_height() - 2.0 * inset,
+            band * 2.0,
+            BLACK.with_alpha(intensity * falloff.powi(3) * 0.5),
+        );
+    });
+}
+
+// Menus and the HUD are laid out in screen space, which follows the real window size.
+fn screen_size() -> Vec2 {
+    vec2(screen_width(), screen_height())
+}
+
+fn draw_toast(message: &str, theme: &Theme) {
+    let text_center = get_text_center(message, None, FONT_SIZE / 2, 1., 0.);
     draw_text(
-        &timer_text,
-        WINDOW_DIMENSIONS.x - text_center.x * 2. - 5.,
-        FONT_SIZE as f32 * 0.5,
+        message,
+        screen_width() * 0.5 - text_center.x,
+        screen_height() - FONT_SIZE as f32 * 0.5,
         FONT_SIZE as f32 * 0.5,
-        TEXT_COLOR,
+        theme.text,
     );
 }
 
-struct DeltaTime(VecDeque<f64>);
+fn draw_time(game: &Game, settings: &GameSettings) {
+    let mut lines = vec![format!("{:.2?}s", game.timer.current())];
+    if let Some(split) = game.timer.laps().last() {
+        lines.push(format!("Split {:.2?}s", split));
+    }
+    if game.floors > 1 {
+        lines.push(format!("Floor {}/{}", game.floor, game.floors));
+    }
+    if !game.target_keys.is_empty() {
+        lines.push(format!("Keys left {}", game.target_keys.len()));
+    }
+    if settings.slow_motion {
+        lines.push(format!("Slow-mo x{:.2}", settings.time_scale));
+    }
+    lines.iter().enumerate().for_each(|(i, text)| {
+        let text_center = get_text_center(text, None, FONT_SIZE / 2, 1., 0.);
+        draw_text(
+            text,
+            screen_width() - text_center.x * 2. - 5.,
+            FONT_SIZE as f32 * 0.5 * (i + 1) as f32,
+            FONT_SIZE as f32 * 0.5,
+            settings.theme().text,
+        );
+    });
+}
+
+struct DeltaTime {
+    samples: VecDeque<f64>,
+    capacity: usize,
+}
 
 impl DeltaTime {
-    fn new() -> Self {
-        Self(VecDeque::with_capacity(120))
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
     }
     fn push(&mut self, time: f64) {
-        while self.0.len() >= 120 {
-            self.0.pop_front();
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
         }
-        self.0.push_back(time);
+        self.samples.push_back(time);
     }
     fn delta_time(&self) -> Option<f64> {
-        match self.0.len() {
+        match self.samples.len() {
             0 => None,
-            _ => Some(self.0.iter().sum::<f64>() / self.0.len() as f64),
+            _ => Some(self.samples.iter().sum::<f64>() / self.samples.len() as f64),
+        }
+    }
+    fn min(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::min)
+    }
+    fn max(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::max)
+    }
+    fn percentile(&self, p: f64) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted = self.samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_time_percentiles() {
+        let mut delta_time = DeltaTime::new(10);
+        assert_eq!(delta_time.percentile(50.0), None);
+        // Pushed out of order and past the capacity, so 0.0 and 100.0 are dropped again.
+        [
+            0.0, 100.0, 7.0, 3.0, 10.0, 1.0, 9.0, 2.0, 8.0, 5.0, 4.0, 6.0,
+        ]
+        .into_iter()
+        .for_each(|time| delta_time.push(time));
+        assert_eq!(delta_time.delta_time(), Some(5.5));
+        assert_eq!(delta_time.min(), Some(1.0));
+        assert_eq!(delta_time.max(), Some(10.0));
+        assert_eq!(delta_time.percentile(0.0), Some(1.0));
+        assert_eq!(delta_time.percentile(10.0), Some(1.0));
+        assert_eq!(delta_time.percentile(50.0), Some(5.0));
+        assert_eq!(delta_time.percentile(95.0), Some(10.0));
+        assert_eq!(delta_time.percentile(99.0), Some(10.0));
+        assert_eq!(delta_time.percentile(250.0), Some(10.0));
+    }
+
+    fn share_settings() -> GameSettings {
+        GameSettings::new(true, false, 1, 0.1, 10.0, 10.0)
+    }
+
+    #[test]
+    fn share_code_round_trip() {
+        let mut settings = share_settings();
+        settings.custom_grid_size = Some(12);
+        settings.dropout = 0.3;
+        settings.algorithm = ALGORITHMS[2].1;
+        settings.braid = 0.5;
+        settings.dimensions = vec2(1000.0, 700.0);
+        settings.cell_aspect = 1.5;
+        settings.placement = Placement::Farthest;
+        settings.min_separation = 0.4;
+        settings.keys_and_doors = true;
+        settings.target_keys = 3;
+        settings.dynamic_walls = true;
+        let code = settings.share_code(42);
+        let mut applied = share_settings();
+        assert_eq!(applied.apply_share_code(&code), Some(42));
+        assert_eq!(applied.share_code(42), code);
+        assert_eq!(applied.custom_grid_size, Some(12));
+        assert!(applied.algorithm == ALGORITHMS[2].1);
+        assert_eq!(applied.dimensions, vec2(1000.0, 700.0));
+        assert_eq!(applied.cell_aspect, 1.5);
+        assert!(applied.placement == Placement::Farthest);
+        assert_eq!(applied.min_separation, 0.4);
+        assert!(applied.keys_and_doors);
+        assert_eq!(applied.target_keys, 3);
+        assert!(applied.dynamic_walls);
+    }
+
+    #[test]
+    fn share_code_defaults_missing_parts() {
+        let mut settings = share_settings();
+        settings.braid = 0.5;
+        settings.dimensions = vec2(1000.0, 700.0);
+        settings.cell_aspect = 1.5;
+        settings.placement = Placement::Farthest;
+        settings.min_separation = 0.4;
+        settings.keys_and_doors = true;
+        settings.target_keys = 3;
+        settings.dynamic_walls = true;
+        // Older codes stop early, whatever is missing falls back to the default.
+        assert_eq!(settings.apply_share_code("7:2:0.2"), Some(7));
+        assert_eq!(settings.braid, 0.0);
+        assert_eq!(settings.dimensions, WINDOW_DIMENSIONS);
+        assert_eq!(settings.cell_aspect, CELL_ASPECT);
+        assert!(settings.placement == Placement::Random);
+        assert_eq!(settings.min_separation, MIN_SEPARATION);
+        assert!(!settings.keys_and_doors);
+        assert_eq!(settings.target_keys, 0);
+        assert!(!settings.dynamic_walls);
+        assert_eq!(settings.share_code(7), "7:2:0.2:0:0");
+    }
+
+    #[test]
+    fn share_code_rejects_invalid() {
+        for code in [
+            "",
+            "x:1:0.2",
+            "7:99:0.2",
+            "7:1:1.5",
+            "7:1:0.2:0:0:q",
+            "7:1:0.2:0:0:a",
+            "7:1:0.2:0:0:a3",
+            "7:1:0.2:0:0:p9",
+            "7:1:0.2:0:0:s0.9",
+            "7:1:0.2:0:0:k1",
+            "7:1:0.2:0:0:t2",
+            "7:1:0.2:0:0:gate",
+            "7:1:0.2:0:0:10x10",
+        ] {
+            assert_eq!(share_settings().apply_share_code(code), None, "{}", code);
         }
     }
+
+    #[test]
+    fn settings_build_a_game() {
+        let mut settings = GameSettings::new(true, false, 2, 0.3, 8.0, 12.0);
+        settings.wall_memory = false;
+        assert_eq!(settings.labyrinth_size, 2);
+        assert_eq!(settings.dropout, 0.3);
+        assert_eq!(settings.target_radius, 12.0);
+        // The fields the menu changes between games.
+        settings.labyrinth_size = 1;
+        settings.dropout = 0.5;
+        settings.target_radius = 15.0;
+        let game = Game::new(&settings);
+        assert_eq!(game.cell_size, settings.cell_size());
+        assert_eq!(game.player_radius, 8.0);
+        assert_eq!(game.target_radius, 15.0);
+    }
 }
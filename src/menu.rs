@@ -1,15 +1,184 @@
 use macroquad::prelude::*;
-use macroquad::ui::widgets::{Checkbox, ComboBox};
-use macroquad::ui::{Skin, hash, root_ui};
+use macroquad::ui::widgets::{Checkbox, ComboBox, Group, InputText, Slider, Window};
+use macroquad::ui::{Id, Skin, Ui, hash, root_ui};
+use std::mem::discriminant;
 
+use crate::bindings::{ACTIONS, Action};
+use crate::clipboard::Clipboard;
+use crate::error::Error;
 use crate::game::Game;
-use crate::{GRID_SIZES, GameState, Settings, WINDOW_DIMENSIONS};
+use crate::gamepad::Navigation;
+use crate::replay::{Playback, Replay};
+use crate::scores::{LEADERBOARD_SIZE, Scores};
+use crate::stats::SessionStats;
+use crate::theme::THEMES;
+use crate::{
+    ALGORITHMS, BACKGROUNDS, BOUNCES, DIFFICULTIES, Difficulty, FPS_CAPS, GRID_SIZES, GameSettings,
+    GameState, LIGHT_RENDERS, MAX_CELL_ASPECT, MAX_GRID_SIZE, MAX_SEPARATION, MAX_VISION_RANGE,
+    MAX_ZOOM, MIN_CELL_ASPECT, MIN_CONE_ANGLE, MIN_GRID_SIZE, MIN_RADIUS, MIN_VISION_RANGE,
+    MIN_ZOOM, MOVEMENT_MODES, PLACEMENTS, RAY_COUNTS, TARGET_KEYS, VISION_MODES, VisionMode,
+    screen_size,
+};
+
+pub struct MenuState {
+    pub display_options_menu: bool,
+    pub display_bindings_menu: bool,
+    pub display_leaderboard_menu: bool,
+    pub beat_ghost: bool,
+    pub rank: Option<usize>,
+    pub confirm: Option<Confirm>,
+    pub stats: SessionStats,
+    pub scores: Scores,
+    pub focus: Focus,
+    pub playback: Option<Playback>,
+    leaderboard_size: usize,
+    seed_input: String,
+    rebinding: Option<(&'static str, Action)>,
+    binding_conflict: Option<KeyCode>,
+    share_code: Option<String>,
+    clipboard: Box<dyn Clipboard>,
+}
+
+impl MenuState {
+    pub fn new(clipboard: Box<dyn Clipboard>, settings: &GameSettings) -> Self {
+        Self {
+            display_options_menu: false,
+            display_bindings_menu: false,
+            display_leaderboard_menu: false,
+            beat_ghost: false,
+            rank: None,
+            confirm: None,
+            stats: SessionStats::new(),
+            scores: Scores::load(settings),
+            focus: Focus::new(),
+            playback: None,
+            leaderboard_size: 0,
+            seed_input: String::new(),
+            rebinding: None,
+            binding_conflict: None,
+            share_code: None,
+            clipboard,
+        }
+    }
+
+    fn share(&mut self, code: String) {
+        self.share_code = match self.clipboard.set(&code) {
+            true => None,
+            false => Some(code),
+        };
+    }
+
+    fn share_button(&mut self, ui: &mut Ui, position: Vec2, code: String) {
+        match &mut self.share_code {
+            Some(share_code) => InputText::new(hash!())
+                .position(position)
+                .size(vec2(340., 40.))
+                .ui(ui, share_code),
+            None => {
+                if self.focus.button(ui, position + vec2(45., 0.), "Share") {
+                    self.share(code);
+                }
+            }
+        }
+    }
+}
+
+pub struct Focus {
+    index: Option<usize>,
+    buttons: usize,
+    activate: bool,
+}
+
+impl Focus {
+    fn new() -> Self {
+        Self {
+            index: None,
+            buttons: 0,
+            activate: false,
+        }
+    }
+
+    pub fn navigate(&mut self, navigation: Option<Navigation>) {
+        let count = self.buttons.max(1);
+        let index = self.index.filter(|index| *index < count);
+        self.index = match navigation {
+            Some(Navigation::Up) => Some(index.map_or(count - 1, |i| (i + count - 1) % count)),
+            Some(Navigation::Down) => Some(index.map_or(0, |i| (i + 1) % count)),
+            _ => index,
+        };
+        self.activate = navigation == Some(Navigation::Activate);
+        self.buttons = 0;
+    }
+
+    fn button(&mut self, ui: &mut Ui, position: Vec2, label: &str) -> bool {
+        let focused = self.index == Some(self.buttons);
+        self.buttons += 1;
+        if focused {
+            ui.label(vec2((position.x - 25.).max(0.), position.y + 20.), ">");
+        }
+        ui.button(position, label) || (focused && self.activate)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Confirm {
+    Exit,
+    QuitGame,
+}
+
+impl Confirm {
+    fn question(&self) -> &'static str {
+        match self {
+            Confirm::Exit => "Exit the game?",
+            Confirm::QuitGame => "Quit this run?",
+        }
+    }
+}
+
+pub struct ConfirmDialog<'a> {
+    question: &'a str,
+}
+
+impl<'a> ConfirmDialog<'a> {
+    pub fn new(question: &'a str) -> Self {
+        Self { question }
+    }
+
+    pub fn display(self, focus: &mut Focus) -> Option<bool> {
+        let mut answer = None;
+        let window_size = vec2(400., 320.);
+        centered_window(hash!(), window_size, |ui| {
+            ui.label(vec2(80., -34.), "Confirm");
+            ui.label(vec2(25., 25.), self.question);
+
+            if focus.button(ui, vec2(95., 75.), "Yes") {
+                answer = Some(true);
+            }
+
+            if focus.button(ui, vec2(120., 175.), "No") {
+                answer = Some(false);
+            }
+        });
+        answer
+    }
+}
+
+// Not movable, so the position is taken every frame and menus stay centered after a resize.
+fn centered_window(id: Id, window_size: Vec2, f: impl FnOnce(&mut Ui)) {
+    Window::new(id, (screen_size() - window_size) * 0.5, window_size)
+        .titlebar(false)
+        .movable(false)
+        .ui(&mut root_ui(), f);
+}
 
 pub enum Menus {
     Main,
     Options,
+    Bindings,
+    Leaderboard,
     Pause,
     GameOver,
+    Lost,
 }
 
 impl Menus {
@@ -17,151 +186,723 @@ impl Menus {
         self,
         game: &mut Game,
         game_state: &mut GameState,
-        settings: &mut Settings,
-        display_options_menu: &mut bool,
+        settings: &mut GameSettings,
+        menu_state: &mut MenuState,
     ) {
+        if let Some(confirm) = menu_state.confirm {
+            match ConfirmDialog::new(confirm.question()).display(&mut menu_state.focus) {
+                Some(true) => match confirm {
+                    Confirm::Exit => {
+                        // Changes still waiting for the settings to settle are saved now.
+                        if let Err(e) = settings.save() {
+                            error!("Could not save settings: {}", e);
+                        }
+                        std::process::exit(0)
+                    }
+                    Confirm::QuitGame => {
+                        menu_state.stats.quit(game.timer.elapsed());
+                        game.timer.reset();
+                        *game_state = GameState::MainMenu;
+                        menu_state.confirm = None;
+                    }
+                },
+                Some(false) => menu_state.confirm = None,
+                None => {}
+            }
+            return;
+        }
+
         match self {
             Menus::Main => {
-                let window_size = vec2(370., 420.);
-                root_ui().window(
-                    hash!(),
-                    (WINDOW_DIMENSIONS - window_size) * 0.5,
-                    window_size,
-                    |ui| {
-                        ui.label(vec2(80.0, -34.0), "Main Menu");
-
-                        if ui.button(vec2(65., 25.), "Play") {
-                            *game = Game::new(
-                                GRID_SIZES[settings.labyrinth_size],
-                                settings.dropout,
-                                settings.target_threshold,
-                            );
-                            game.timer.start();
-                            *game_state = GameState::Playing;
-                        }
+                let window_size = vec2(370., 720.);
+                centered_window(hash!(), window_size, |ui| {
+                    ui.label(vec2(80.0, -34.0), "Main Menu");
 
-                        if ui.button(vec2(20., 125.), "Options") {
-                            *display_options_menu = true;
+                    if menu_state.focus.button(ui, vec2(65., 25.), "Play") {
+                        *game = Game::new(settings);
+                        if let Err(e) = game.timer.start() {
+                            error!("{}", e);
                         }
+                        *game_state = GameState::Playing;
+                        menu_state.share_code = None;
+                    }
 
-                        if ui.button(vec2(65.0, 225.0), "Quit") {
-                            std::process::exit(0);
+                    if menu_state.focus.button(ui, vec2(50., 115.), "Paste") {
+                        let seed = menu_state
+                            .clipboard
+                            .get()
+                            .and_then(|code| settings.apply_share_code(&code));
+                        if let Some(seed) = seed {
+                            *game = Game::with_seed(settings, seed);
+                            if let Err(e) = game.timer.start() {
+                                error!("{}", e);
+                            }
+                            *game_state = GameState::Playing;
+                            menu_state.share_code = None;
                         }
-                    },
-                );
+                    }
+
+                    if menu_state.focus.button(ui, vec2(20., 205.), "Options") {
+                        menu_state.display_options_menu = true;
+                    }
+
+                    if menu_state.focus.button(ui, vec2(40., 295.), "Scores") {
+                        menu_state.leaderboard_size = settings.labyrinth_size;
+                        menu_state.display_leaderboard_menu = true;
+                    }
+
+                    if menu_state.focus.button(ui, vec2(40., 385.), "Replay")
+                        && let Some((replay_settings, replay)) = Replay::load(settings)
+                    {
+                        *game = Game::with_seed(&replay_settings, replay.seed);
+                        menu_state.playback = Some(Playback::new(replay, replay_settings));
+                        *game_state = GameState::Watching;
+                    }
+
+                    if menu_state.focus.button(ui, vec2(65.0, 475.0), "Quit") {
+                        menu_state.confirm = Some(Confirm::Exit);
+                    }
+
+                    menu_state
+                        .stats
+                        .lines()
+                        .iter()
+                        .enumerate()
+                        .for_each(|(i, line)| {
+                            ui.label(vec2(25., 565. + 35. * i as f32), line);
+                        });
+                });
             }
             Menus::Options => {
-                let window_size = vec2(420., 375.);
-                root_ui().window(
-                    hash!(),
-                    (WINDOW_DIMENSIONS - window_size) * 0.5,
-                    window_size,
-                    |ui| {
-                        ui.label(vec2(80.0, -34.0), "Options Menu");
+                let window_size = vec2(420., 660.);
+                centered_window(hash!(), window_size, |ui| {
+                    ui.label(vec2(80.0, -34.0), "Options Menu");
 
-                        ComboBox::new(hash!(), &["small", "medium", "large"])
-                            .label("Labyrinth Size")
-                            .ui(ui, &mut settings.labyrinth_size);
+                    Group::new(hash!(), vec2(380., 380.)).ui(ui, |ui| {
+                        let mut difficulty = DIFFICULTIES
+                            .iter()
+                            .position(|(_, difficulty)| *difficulty == settings.difficulty)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &DIFFICULTIES.map(|(name, _)| name))
+                            .label("Difficulty")
+                            .ui(ui, &mut difficulty);
+                        settings.difficulty = DIFFICULTIES[difficulty].1;
+                        settings.apply_difficulty();
+                        let custom = settings.difficulty == Difficulty::Custom;
+
+                        if custom {
+                            ComboBox::new(hash!(), &GRID_SIZES.map(|(name, _)| name))
+                                .label("Labyrinth Size")
+                                .ui(ui, &mut settings.labyrinth_size);
+
+                            let mut custom_size = settings.custom_grid_size.is_some();
+                            Checkbox::new(hash!())
+                                .pos(vec2(-110., 0.0))
+                                .label("Custom Size")
+                                .ui(ui, &mut custom_size);
+                            settings.custom_grid_size = custom_size.then(|| {
+                                let mut grid_size = settings
+                                    .custom_grid_size
+                                    .unwrap_or(GRID_SIZES[settings.labyrinth_size].1)
+                                    as f32;
+                                Slider::new(hash!(), MIN_GRID_SIZE as f32..MAX_GRID_SIZE as f32)
+                                    .label("Cell Size")
+                                    .ui(ui, &mut grid_size);
+                                grid_size.round() as usize
+                            });
+                        }
+
+                        let mut algorithm = ALGORITHMS
+                            .iter()
+                            .position(|(_, algorithm)| *algorithm == settings.algorithm)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &ALGORITHMS.map(|(name, _)| name))
+                            .label("Algorithm")
+                            .ui(ui, &mut algorithm);
+                        settings.algorithm = ALGORITHMS[algorithm].1;
+
+                        InputText::new(hash!())
+                            .label("Seed")
+                            .ui(ui, &mut menu_state.seed_input);
+                        settings.seed = menu_state.seed_input.trim().parse().ok();
+
+                        let mut placement = PLACEMENTS
+                            .iter()
+                            .position(|(_, placement)| *placement == settings.placement)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &PLACEMENTS.map(|(name, _)| name))
+                            .label("Placement")
+                            .ui(ui, &mut placement);
+                        settings.placement = PLACEMENTS[placement].1;
+
+                        ComboBox::new(hash!(), &THEMES.map(|(name, _)| name))
+                            .label("Theme")
+                            .ui(ui, &mut settings.theme);
+
+                        let mut light_render = LIGHT_RENDERS
+                            .iter()
+                            .position(|(_, render)| *render == settings.light_render)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &LIGHT_RENDERS.map(|(name, _)| name))
+                            .label("Light")
+                            .ui(ui, &mut light_render);
+                        settings.light_render = LIGHT_RENDERS[light_render].1;
+
+                        let mut vision_mode = VISION_MODES
+                            .iter()
+                            .position(|(_, mode)| {
+                                discriminant(mode) == discriminant(&settings.vision_mode)
+                            })
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &VISION_MODES.map(|(name, _)| name))
+                            .label("View")
+                            .ui(ui, &mut vision_mode);
+                        if discriminant(&VISION_MODES[vision_mode].1)
+                            != discriminant(&settings.vision_mode)
+                        {
+                            settings.vision_mode = VISION_MODES[vision_mode].1;
+                        }
+                        if let VisionMode::Cone { half_angle } = &mut settings.vision_mode {
+                            Slider::new(hash!(), MIN_CONE_ANGLE..180.)
+                                .label("Cone Angle")
+                                .ui(ui, half_angle);
+                        }
+
+                        let mut bounces = BOUNCES
+                            .iter()
+                            .position(|(_, count)| *count == settings.bounces)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &BOUNCES.map(|(name, _)| name))
+                            .label("Bounces")
+                            .ui(ui, &mut bounces);
+                        settings.bounces = BOUNCES[bounces].1;
+
+                        let mut ray_count = RAY_COUNTS
+                            .iter()
+                            .position(|(_, count)| *count == settings.ray_count)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &RAY_COUNTS.map(|(name, _)| name))
+                            .label("Rays")
+                            .ui(ui, &mut ray_count);
+                        settings.ray_count = RAY_COUNTS[ray_count].1;
+
+                        let mut movement_mode = MOVEMENT_MODES
+                            .iter()
+                            .position(|(_, mode)| *mode == settings.movement_mode)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &MOVEMENT_MODES.map(|(name, _)| name))
+                            .label("Movement")
+                            .ui(ui, &mut movement_mode);
+                        settings.movement_mode = MOVEMENT_MODES[movement_mode].1;
+
+                        let mut background = BACKGROUNDS
+                            .iter()
+                            .position(|(_, style)| *style == settings.background)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &BACKGROUNDS.map(|(name, _)| name))
+                            .label("Background")
+                            .ui(ui, &mut background);
+                        settings.background = BACKGROUNDS[background].1;
+
+                        let mut fps_cap = FPS_CAPS
+                            .iter()
+                            .position(|(_, cap)| *cap == settings.fps_cap)
+                            .unwrap_or(FPS_CAPS.len() - 1);
+                        ComboBox::new(hash!(), &FPS_CAPS.map(|(name, _)| name))
+                            .label("FPS Cap")
+                            .ui(ui, &mut fps_cap);
+                        settings.fps_cap = FPS_CAPS[fps_cap].1;
 
                         Checkbox::new(hash!())
-                            .pos(vec2(-110., 25.0))
+                            .pos(vec2(-110., 0.0))
                             .label("Display Labyrinth")
                             .ui(ui, &mut settings.draw_labyrinth);
 
                         Checkbox::new(hash!())
-                            .pos(vec2(-110., 50.0))
+                            .pos(vec2(-110., 0.0))
+                            .label("Practice")
+                            .ui(ui, &mut settings.practice);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Campaign")
+                            .ui(ui, &mut settings.campaign);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Time Attack")
+                            .ui(ui, &mut settings.countdown);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
                             .label("Display dt")
                             .ui(ui, &mut settings.draw_delta_time);
 
-                        if ui.button(vec2(65., 175.), "Back") {
-                            *display_options_menu = false;
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Frame Stats")
+                            .ui(ui, &mut settings.draw_frame_stats);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Grid Coords")
+                            .ui(ui, &mut settings.draw_grid_coords);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Keys & Doors")
+                            .ui(ui, &mut settings.keys_and_doors);
+
+                        let mut target_keys = TARGET_KEYS
+                            .iter()
+                            .position(|(_, count)| *count == settings.target_keys)
+                            .unwrap_or(0);
+                        ComboBox::new(hash!(), &TARGET_KEYS.map(|(name, _)| name))
+                            .label("Target Keys")
+                            .ui(ui, &mut target_keys);
+                        settings.target_keys = TARGET_KEYS[target_keys].1;
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Wandering Target")
+                            .ui(ui, &mut settings.wandering_target);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Wall Memory")
+                            .ui(ui, &mut settings.wall_memory);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Moving Walls")
+                            .ui(ui, &mut settings.dynamic_walls);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Proximity Cue")
+                            .ui(ui, &mut settings.proximity_cue);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Breadcrumbs")
+                            .ui(ui, &mut settings.trail);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Minimap")
+                            .ui(ui, &mut settings.minimap);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Follow Camera")
+                            .ui(ui, &mut settings.camera_follow);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Aspect Lock")
+                            .ui(ui, &mut settings.aspect_lock);
+
+                        Checkbox::new(hash!())
+                            .pos(vec2(-110., 0.0))
+                            .label("Slow Motion")
+                            .ui(ui, &mut settings.slow_motion);
+
+                        Slider::new(hash!(), MIN_RADIUS..20.)
+                            .label("Player")
+                            .ui(ui, &mut settings.player_radius);
+
+                        if custom {
+                            Slider::new(hash!(), MIN_RADIUS..30.)
+                                .label("Target")
+                                .ui(ui, &mut settings.target_radius);
                         }
-                    },
-                );
+
+                        Slider::new(hash!(), 1.0..6.)
+                            .label("Walls")
+                            .ui(ui, &mut settings.wall_thickness);
+
+                        if custom {
+                            Slider::new(hash!(), 0.0..1.)
+                                .label("Dropout")
+                                .ui(ui, &mut settings.dropout);
+                        }
+
+                        Slider::new(hash!(), 0.0..1.)
+                            .label("Braid")
+                            .ui(ui, &mut settings.braid);
+
+                        Slider::new(hash!(), MIN_VISION_RANGE..MAX_VISION_RANGE)
+                            .label("Vision")
+                            .ui(ui, &mut settings.vision_range);
+
+                        Slider::new(hash!(), 0.0..MAX_SEPARATION)
+                            .label("Separation")
+                            .ui(ui, &mut settings.min_separation);
+
+                        Slider::new(hash!(), MIN_CELL_ASPECT..MAX_CELL_ASPECT)
+                            .label("Cell Aspect")
+                            .ui(ui, &mut settings.cell_aspect);
+
+                        Slider::new(hash!(), 0.1..3.)
+                            .label("Speed")
+                            .ui(ui, &mut settings.move_speed);
+
+                        Slider::new(hash!(), 0.1..3.)
+                            .label("Mouse")
+                            .ui(ui, &mut settings.mouse_sensitivity);
+
+                        Slider::new(hash!(), 0.0..20.)
+                            .label("Dead Zone")
+                            .ui(ui, &mut settings.dead_zone);
+
+                        Slider::new(hash!(), 0.0..0.5)
+                            .label("Stick Zone")
+                            .ui(ui, &mut settings.stick_dead_zone);
+
+                        Slider::new(hash!(), 0.5..10.)
+                            .label("Nudge")
+                            .ui(ui, &mut settings.nudge_step);
+
+                        Slider::new(hash!(), 0.0..3.)
+                            .label("Celebration")
+                            .ui(ui, &mut settings.win_animation);
+
+                        Slider::new(hash!(), MIN_ZOOM..MAX_ZOOM)
+                            .label("Zoom")
+                            .ui(ui, &mut settings.zoom);
+
+                        Slider::new(hash!(), 0.1..1.)
+                            .label("Time Scale")
+                            .ui(ui, &mut settings.time_scale);
+
+                        Slider::new(hash!(), 0.0..1.)
+                            .label("Vignette")
+                            .ui(ui, &mut settings.vignette);
+                    });
+
+                    if menu_state.focus.button(ui, vec2(65., 400.), "Keys") {
+                        menu_state.display_bindings_menu = true;
+                    }
+
+                    if menu_state.focus.button(ui, vec2(65., 500.), "Back") {
+                        menu_state.display_options_menu = false;
+                    }
+                });
             }
-            Menus::Pause => {
-                let window_size = vec2(400., 420.);
-                root_ui().window(
-                    hash!(),
-                    (WINDOW_DIMENSIONS - window_size) * 0.5,
-                    window_size,
-                    |ui| {
-                        ui.label(vec2(80., -34.), "Pause Menu");
-
-                        if ui.button(vec2(25., 25.), "Continue") {
-                            game.timer.resume();
-                            *game_state = GameState::Playing;
+            Menus::Bindings => {
+                match menu_state.rebinding {
+                    Some((_, action)) => {
+                        if let Some(key) = get_last_key_pressed() {
+                            menu_state.binding_conflict =
+                                match settings.key_bindings.bind(action, key) {
+                                    true => None,
+                                    false => Some(key),
+                                };
+                            menu_state.rebinding = None;
                         }
+                    }
+                    None => {
+                        let digits = [
+                            KeyCode::Key1,
+                            KeyCode::Key2,
+                            KeyCode::Key3,
+                            KeyCode::Key4,
+                            KeyCode::Key5,
+                            KeyCode::Key6,
+                            KeyCode::Key7,
+                            KeyCode::Key8,
+                            KeyCode::Key9,
+                            KeyCode::Key0,
+                        ];
+                        menu_state.rebinding = digits
+                            .iter()
+                            .zip(ACTIONS)
+                            .find(|(digit, _)| is_key_pressed(**digit))
+                            .map(|(_, action)| action);
+                    }
+                }
 
-                        if ui.button(vec2(25., 125.), "New Game") {
-                            *game = Game::new(
-                                GRID_SIZES[settings.labyrinth_size],
-                                settings.dropout,
-                                settings.target_threshold,
-                            );
-                            game.timer.start();
-                            *game_state = GameState::Playing;
+                let window_size = vec2(520., 640.);
+                centered_window(hash!(), window_size, |ui| {
+                    ui.label(vec2(80., -34.), "Key Bindings");
+
+                    ACTIONS.iter().enumerate().for_each(|(i, (name, action))| {
+                        ui.label(
+                            vec2(25., 25. + 35. * i as f32),
+                            &format!(
+                                "{} {}: {:?}",
+                                (i + 1) % 10,
+                                name,
+                                settings.key_bindings.key(*action)
+                            ),
+                        );
+                    });
+
+                    let hint = match (menu_state.rebinding, menu_state.binding_conflict) {
+                        (Some((name, _)), _) => format!("Press a key for {}", name),
+                        (None, Some(key)) => format!("{:?} is already bound", key),
+                        (None, None) => "Press 0-9 to rebind".to_owned(),
+                    };
+                    ui.label(vec2(25., 370.), &hint);
+
+                    if menu_state.focus.button(ui, vec2(115., 455.), "Back") {
+                        menu_state.display_bindings_menu = false;
+                        menu_state.rebinding = None;
+                        menu_state.binding_conflict = None;
+                    }
+                });
+            }
+            Menus::Leaderboard => {
+                let digits = [
+                    KeyCode::Key1,
+                    KeyCode::Key2,
+                    KeyCode::Key3,
+                    KeyCode::Key4,
+                    KeyCode::Key5,
+                    KeyCode::Key6,
+                    KeyCode::Key7,
+                    KeyCode::Key8,
+                    KeyCode::Key9,
+                    KeyCode::Key0,
+                ];
+                let entry = digits
+                    .iter()
+                    .zip(menu_state.scores.leaderboard(menu_state.leaderboard_size))
+                    .find(|(digit, _)| is_key_pressed(**digit))
+                    .map(|(_, entry)| (entry.seed, entry.maze.clone()));
+                if let Some((seed, maze)) = entry
+                    && settings.apply_share_code(&maze).is_some()
+                {
+                    *game = Game::with_seed(settings, seed);
+                    if let Err(e) = game.timer.start() {
+                        error!("{}", e);
+                    }
+                    *game_state = GameState::Playing;
+                    menu_state.display_leaderboard_menu = false;
+                    menu_state.share_code = None;
+                    return;
+                }
+
+                let window_size = vec2(560., 680.);
+                centered_window(hash!(), window_size, |ui| {
+                    ui.label(vec2(80., -34.), "Leaderboard");
+
+                    Group::new(hash!(), vec2(520., 50.))
+                        .position(vec2(0., 0.))
+                        .ui(ui, |ui| {
+                            ComboBox::new(hash!(), &GRID_SIZES.map(|(name, _)| name))
+                                .label("Labyrinth Size")
+                                .ui(ui, &mut menu_state.leaderboard_size);
+                        });
+
+                    let entries = menu_state.scores.leaderboard(menu_state.leaderboard_size);
+                    if entries.is_empty() {
+                        ui.label(vec2(25., 70.), "No times yet");
+                    }
+                    entries.iter().enumerate().for_each(|(i, entry)| {
+                        ui.label(
+                            vec2(25., 70. + 35. * i as f32),
+                            &format!("{:>2}. {:.2}s  {}", i + 1, entry.time, entry.date()),
+                        );
+                    });
+
+                    if !entries.is_empty() {
+                        ui.label(
+                            vec2(25., 70. + 35. * LEADERBOARD_SIZE as f32),
+                            "Press a rank to replay its seed",
+                        );
+                    }
+
+                    if menu_state.focus.button(ui, vec2(135., 520.), "Back") {
+                        menu_state.display_leaderboard_menu = false;
+                    }
+                });
+            }
+            Menus::Pause => {
+                let window_size = vec2(400., 620.);
+                centered_window(hash!(), window_size, |ui| {
+                    ui.label(vec2(80., -34.), "Pause Menu");
+
+                    if menu_state.focus.button(ui, vec2(25., 25.), "Continue") {
+                        if let Err(e) = game.timer.resume() {
+                            error!("{}", e);
                         }
+                        *game_state = GameState::Playing;
+                    }
+
+                    if menu_state.focus.button(ui, vec2(40., 125.), "Restart") {
+                        menu_state.stats.quit(game.timer.elapsed());
+                        game.restart(settings);
+                        *game_state = GameState::Playing;
+                    }
 
-                        if ui.button(vec2(5., 225.), "Quit Game") {
-                            *game_state = GameState::MainMenu;
-                            game.timer.stop();
+                    if menu_state.focus.button(ui, vec2(25., 225.), "New Game") {
+                        menu_state.stats.quit(game.timer.elapsed());
+                        *game = Game::new(settings);
+                        if let Err(e) = game.timer.start() {
+                            error!("{}", e);
                         }
-                    },
-                );
+                        *game_state = GameState::Playing;
+                        menu_state.share_code = None;
+                    }
+
+                    menu_state.share_button(ui, vec2(25., 325.), settings.share_code(game.seed));
+
+                    if menu_state.focus.button(ui, vec2(5., 425.), "Quit Game") {
+                        menu_state.confirm = Some(Confirm::QuitGame);
+                    }
+
+                    ui.label(vec2(25., 525.), &format!("Seed {}", game.seed));
+                });
             }
             Menus::GameOver => {
-                let window_size = vec2(400., 370.);
-                root_ui().window(
-                    hash!(),
-                    (WINDOW_DIMENSIONS - window_size) * 0.5,
-                    window_size,
-                    |ui| {
-                        ui.label(vec2(80., -34.), "Main Menu");
+                let window_size = vec2(880., 720.);
+                centered_window(hash!(), window_size, |ui| {
+                    ui.label(vec2(120., -34.), "Main Menu");
+
+                    ui.label(
+                        vec2(65., 25.),
+                        format!(
+                            "{} {}",
+                            match menu_state.beat_ghost {
+                                true => "Ghost Beaten!",
+                                false => "You Won!",
+                            },
+                            format_result(game.timer.result)
+                        )
+                        .as_str(),
+                    );
 
+                    match (game.timer.practice, menu_state.rank) {
+                        (true, _) => ui.label(vec2(25., 65.), "(practice - not recorded)"),
+                        (false, Some(rank)) => {
+                            ui.label(vec2(25., 65.), &format!("New high score #{}!", rank + 1))
+                        }
+                        (false, None) => {}
+                    }
+
+                    ui.label(vec2(500., 25.), "High Scores");
+                    let entries = menu_state.scores.leaderboard(settings.labyrinth_size);
+                    if entries.is_empty() {
+                        ui.label(vec2(500., 65.), "No times yet");
+                    }
+                    entries.iter().enumerate().for_each(|(i, entry)| {
+                        let marker = match menu_state.rank == Some(i) {
+                            true => ">",
+                            false => " ",
+                        };
                         ui.label(
-                            vec2(25., 25.),
-                            format!("You Won! {:.2?}s", game.timer.result.unwrap()).as_str(),
+                            vec2(480., 65. + 35. * i as f32),
+                            &format!("{}{:>2}. {:.2}s", marker, i + 1, entry.time),
                         );
+                    });
 
-                        if ui.button(vec2(25., 75.), "New Game") {
-                            *game_state = GameState::Playing;
-                            *game = Game::new(
-                                GRID_SIZES[settings.labyrinth_size],
-                                settings.dropout,
-                                settings.target_threshold,
-                            );
-                            game.timer.start();
+                    if menu_state.focus.button(ui, vec2(65., 125.), "New Game") {
+                        *game_state = GameState::Playing;
+                        *game = Game::new(settings);
+                        if let Err(e) = game.timer.start() {
+                            error!("{}", e);
                         }
+                        menu_state.share_code = None;
+                    }
+
+                    if menu_state.focus.button(ui, vec2(80., 215.), "Restart") {
+                        *game_state = GameState::Playing;
+                        game.restart(settings);
+                    }
+
+                    if menu_state.focus.button(ui, vec2(100., 305.), "Watch") {
+                        game.rewind();
+                        menu_state.playback =
+                            Some(Playback::new(game.replay.clone(), settings.clone()));
+                        *game_state = GameState::Watching;
+                    }
 
-                        if ui.button(vec2(10., 175.), "Quit Game") {
-                            *game_state = GameState::MainMenu;
+                    menu_state.share_button(ui, vec2(65., 395.), settings.share_code(game.seed));
+
+                    if menu_state.focus.button(ui, vec2(50., 485.), "Quit Game") {
+                        *game_state = GameState::MainMenu;
+                    }
+
+                    menu_state
+                        .stats
+                        .lines()
+                        .iter()
+                        .enumerate()
+                        .for_each(|(i, line)| {
+                            ui.label(vec2(65., 575. + 35. * i as f32), line);
+                        });
+                });
+            }
+            Menus::Lost => {
+                let window_size = vec2(480., 520.);
+                centered_window(hash!(), window_size, |ui| {
+                    ui.label(vec2(120., -34.), "Game Over");
+
+                    ui.label(vec2(65., 25.), "Time's up!");
+
+                    if menu_state.focus.button(ui, vec2(80., 125.), "Restart") {
+                        *game_state = GameState::Playing;
+                        game.restart(settings);
+                    }
+
+                    if menu_state.focus.button(ui, vec2(65., 225.), "New Game") {
+                        *game_state = GameState::Playing;
+                        *game = Game::new(settings);
+                        if let Err(e) = game.timer.start() {
+                            error!("{}", e);
                         }
-                    },
-                );
+                        menu_state.share_code = None;
+                    }
+
+                    if menu_state.focus.button(ui, vec2(50., 325.), "Quit Game") {
+                        *game_state = GameState::MainMenu;
+                    }
+                });
             }
         }
     }
 }
 
 pub async fn make_skin() -> Skin {
-    let window_background = load_image("assets/window_background.png").await.unwrap();
-    let button_background = load_image("assets/button_background.png").await.unwrap();
-    let button_clicked_background = load_image("assets/button_clicked_background.png")
-        .await
-        .unwrap();
-    let checkbox_background = load_image("assets/checkbox_background.png").await.unwrap();
-    // let checkbox_background_selected = load_image("assets/checkbox_background_selected.png")
-    //     .await
-    //     .unwrap();
-    let checkbox_clicked_background = load_image("assets/checkbox_clicked_background.png")
-        .await
-        .unwrap();
-    let checkbox_hovered_background = load_image("assets/checkbox_hovered_background.png")
-        .await
-        .unwrap();
-    let combobox_background = load_image("assets/combobox_background.png").await.unwrap();
-    let font = load_file("assets/atari_games.ttf").await.unwrap();
+    let load = |name: &str| {
+        let path = format!("assets/{}", name);
+        async move { Ok(load_file(&path).await?) }
+    };
+    match load_skin(load).await {
+        Ok(skin) => skin,
+        Err(e) => {
+            error!("Falling back to the default skin: {}", e);
+            root_ui().default_skin()
+        }
+    }
+}
+
+async fn load_asset_image<F>(load: &impl Fn(&str) -> F, name: &str) -> Result<Image, Error>
+where
+    F: Future<Output = Result<Vec<u8>, Error>>,
+{
+    Ok(Image::from_file_with_format(&load(name).await?, None)?)
+}
+
+// Assets come from `load`, so that tests can read them without a window.
+async fn load_skin<F>(load: impl Fn(&str) -> F) -> Result<Skin, Error>
+where
+    F: Future<Output = Result<Vec<u8>, Error>>,
+{
+    let window_background = load_asset_image(&load, "window_background.png").await?;
+    let button_background = load_asset_image(&load, "button_background.png").await?;
+    let button_clicked_background =
+        load_asset_image(&load, "button_clicked_background.png").await?;
+    let checkbox_background = load_asset_image(&load, "checkbox_background.png").await?;
+    // let checkbox_background_selected =
+    //     load_asset_image(&load, "checkbox_background_selected.png").await?;
+    let checkbox_clicked_background =
+        load_asset_image(&load, "checkbox_clicked_background.png").await?;
+    let checkbox_hovered_background =
+        load_asset_image(&load, "checkbox_hovered_background.png").await?;
+    let combobox_background = load_asset_image(&load, "combobox_background.png").await?;
+    let font = load("atari_games.ttf").await?;
 
     let window_style = root_ui()
         .style_builder()
@@ -176,16 +917,14 @@ pub async fn make_skin() -> Skin {
         .background_clicked(button_clicked_background)
         .background_margin(RectOffset::new(16.0, 16.0, 16.0, 16.0))
         .margin(RectOffset::new(16.0, 0.0, -8.0, -8.0))
-        .font(&font)
-        .unwrap()
+        .font(&font)?
         .text_color(WHITE)
         .font_size(64)
         .build();
 
     let label_style = root_ui()
         .style_builder()
-        .font(&font)
-        .unwrap()
+        .font(&font)?
         .text_color(WHITE)
         .font_size(28)
         .build();
@@ -195,8 +934,7 @@ pub async fn make_skin() -> Skin {
         .background(checkbox_background)
         .background_hovered(checkbox_hovered_background)
         .background_clicked(checkbox_clicked_background)
-        .font(&font)
-        .unwrap()
+        .font(&font)?
         .text_color(WHITE)
         .font_size(28)
         .build();
@@ -205,19 +943,53 @@ pub async fn make_skin() -> Skin {
         .style_builder()
         .background(combobox_background)
         .background_margin(RectOffset::new(4., 25., 6., 6.))
-        .font(&font)
-        .unwrap()
+        .font(&font)?
         .text_color(WHITE)
         .color(Color::from_rgba(210, 210, 210, 255))
         .font_size(28)
         .build();
 
-    Skin {
+    Ok(Skin {
         window_style,
         button_style,
         label_style,
         checkbox_style,
         combobox_style,
         ..root_ui().default_skin()
+    })
+}
+
+// A finished maze always has a result, the placeholder only guards against a reset timer.
+fn format_result(result: Option<f64>) -> String {
+    match result {
+        Some(result) => format!("{:.2?}s", result),
+        None => "--:--".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    #[test]
+    fn missing_assets_return_an_error_instead_of_panicking() {
+        let load = |name: &str| {
+            let path = format!("does/not/exist/{}", name);
+            async move { Ok(std::fs::read(path)?) }
+        };
+        let mut skin = pin!(load_skin(load));
+        // Reading from disk never has to wait, so the first poll already finishes.
+        let Poll::Ready(skin) = skin.as_mut().poll(&mut Context::from_waker(Waker::noop())) else {
+            panic!("loading the skin did not finish");
+        };
+        assert!(matches!(skin, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn missing_results_format_as_a_placeholder() {
+        assert_eq!(format_result(None), "--:--");
+        assert_eq!(format_result(Some(12.345)), "12.35s");
     }
 }
@@ -0,0 +1,212 @@
+use macroquad::prelude::get_time;
+use std::fs;
+
+use crate::config::{config_file, write_atomically};
+use crate::error::Error;
+use crate::game::{Game, Grid};
+use crate::geometrie::{Circle, Line, Point};
+use crate::{GameSettings, PLAYBACK_HOLD, REPLAY_TOLERANCE};
+
+#[derive(Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    frames: Vec<(f64, Point<f32>)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, time: f64, position: Point<f32>) {
+        self.frames.push((time, position));
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.frames.last().map_or(0.0, |(time, _)| *time)
+    }
+
+    pub fn save(&self, share_code: &str) -> Result<(), Error> {
+        let Some(path) = config_file("replay.txt") else {
+            return Ok(());
+        };
+        write_atomically(&path, &self.to_text(share_code))
+    }
+
+    // The share code of the maze on the first line, then one "time x y" line per frame.
+    pub fn to_text(&self, share_code: &str) -> String {
+        let frames = self
+            .frames
+            .iter()
+            .map(|(time, p)| format!("{} {} {}\n", time, p.x, p.y))
+            .collect::<String>();
+        format!("{}\n{}", share_code, frames)
+    }
+
+    // Comes with the settings of the recorded maze, the live ones are left alone.
+    pub fn load(settings: &GameSettings) -> Option<(GameSettings, Self)> {
+        let text = fs::read_to_string(config_file("replay.txt")?).ok()?;
+        Self::parse(&text, settings)
+    }
+
+    pub fn parse(text: &str, settings: &GameSettings) -> Option<(GameSettings, Self)> {
+        let mut lines = text.lines();
+        let mut settings = settings.clone();
+        let seed = settings.apply_share_code(lines.next()?)?;
+        // Positions are looked up in the grid, so anything off it means a broken file.
+        let grid = Grid::new(settings.dimensions, settings.cell_size());
+        let frames = lines
+            .map(|line| {
+                let mut parts = line.split_whitespace();
+                let time: f64 = parts.next()?.parse().ok()?;
+                let x = parts.next()?.parse().ok()?;
+                let y = parts.next()?.parse().ok()?;
+                let position = Point::new(x, y);
+                (time.is_finite() && grid.contains(&position)).then_some((time, position))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((settings, Self { seed, frames }))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point<f32>> + '_ {
+        self.frames.iter().map(|(_, p)| *p)
+    }
+
+    pub fn verify(&self, settings: &GameSettings, time: f64) -> bool {
+        let Some(&(last_time, last)) = self.frames.last() else {
+            return false;
+        };
+        let mut game = Game::with_seed(settings, self.seed);
+        let target = game.target_at(last_time);
+        let reach = game.player_radius + game.target_radius;
+        let previous = self
+            .frames
+            .iter()
+            .rev()
+            .nth(1)
+            .map_or(game.spawn, |(_, p)| *p);
+        let last_step = Line::new(previous, last);
+        (last_time - time).abs() <= REPLAY_TOLERANCE
+            && Circle::new(target, reach).intersects_line(&last_step)
+            && self
+                .frames
+                .iter()
+                .all(|&(time, position)| game.replay_move(time, position))
+            && game.target_keys.is_empty()
+    }
+
+    pub fn position_at(&self, time: f64) -> Option<Point<f32>> {
+        let idx = self.frames.partition_point(|(t, _)| *t <= time);
+        match idx {
+            0 => self.frames.first().map(|(_, p)| *p),
+            i if i == self.frames.len() => self.frames.last().map(|(_, p)| *p),
+            i => {
+                let (t1, p1) = self.frames[i - 1];
+                let (t2, p2) = self.frames[i];
+                let t = ((time - t1) / (t2 - t1)) as f32;
+                Some(p1.lerp(&p2, t))
+            }
+        }
+    }
+}
+
+pub struct Playback {
+    pub replay: Replay,
+    pub settings: GameSettings,
+    start: f64,
+}
+
+impl Playback {
+    pub fn new(replay: Replay, settings: GameSettings) -> Self {
+        Self {
+            replay,
+            settings,
+            start: get_time(),
+        }
+    }
+
+    pub fn time(&self) -> f64 {
+        get_time() - self.start
+    }
+
+    pub fn position(&self) -> Option<Point<f32>> {
+        self.replay.position_at(self.time())
+    }
+
+    pub fn finished(&self) -> bool {
+        self.time() > self.replay.duration() + PLAYBACK_HOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CELL_ASPECT;
+
+    #[test]
+    fn parse_keeps_the_settings_to_itself() {
+        let settings = GameSettings::new(true, false, 1, 0.1, 10.0, 10.0);
+        let text = "7:1:0.2:0:0:a1.5\n0 100 100\n0.5 110 100\n";
+        let (replay_settings, replay) = Replay::parse(text, &settings).unwrap();
+        assert_eq!(replay.seed, 7);
+        assert_eq!(replay.duration(), 0.5);
+        assert_eq!(replay_settings.cell_aspect, 1.5);
+        assert_eq!(settings.cell_aspect, CELL_ASPECT);
+    }
+
+    #[test]
+    fn parse_rejects_points_off_the_grid() {
+        let settings = GameSettings::new(true, false, 1, 0.1, 10.0, 10.0);
+        let beyond = format!("0.5 {} 100", settings.dimensions.x + 1.0);
+        for frame in [
+            "0.5 NaN 100",
+            "0.5 100 inf",
+            "0.5 -1 100",
+            "NaN 100 100",
+            &beyond,
+        ] {
+            let text = format!("7:1:0.2\n0 100 100\n{}\n", frame);
+            assert!(Replay::parse(&text, &settings).is_none(), "{}", frame);
+        }
+    }
+
+    fn solved(settings: &GameSettings, seed: u64) -> Replay {
+        let game = Game::with_seed(settings, seed);
+        let mut replay = Replay::new(seed);
+        for (i, position) in game.solve().into_iter().enumerate() {
+            replay.record(0.1 * (i + 1) as f64, position);
+        }
+        replay
+    }
+
+    #[test]
+    fn verify_accepts_a_solved_maze() {
+        let mut settings = GameSettings::new(true, false, 0, 0.1, 10.0, 10.0);
+        settings.wall_memory = false;
+        let replay = solved(&settings, 7);
+        assert!(replay.duration() > 0.0);
+        assert!(replay.verify(&settings, replay.duration()));
+    }
+
+    #[test]
+    fn verify_rejects_cheated_replays() {
+        let mut settings = GameSettings::new(true, false, 0, 0.1, 10.0, 10.0);
+        settings.wall_memory = false;
+        let replay = solved(&settings, 7);
+        assert!(!replay.verify(&settings, replay.duration() + 2.0 * REPLAY_TOLERANCE));
+
+        // Straight to the target, through whatever walls are in the way.
+        let game = Game::with_seed(&settings, 7);
+        assert!(!game.is_legal_move(game.spawn, game.target));
+        let mut jump = Replay::new(7);
+        jump.record(0.1, game.target);
+        assert!(!jump.verify(&settings, 0.1));
+    }
+}
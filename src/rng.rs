@@ -0,0 +1,33 @@
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed with splitmix64 so that small or zero seeds still give a usable state.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self {
+            state: (z ^ (z >> 31)).max(1),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // Callers pick from a non-empty slice, `len` must be above zero.
+    pub fn index(&mut self, len: usize) -> usize {
+        debug_assert!(len > 0, "index into an empty range");
+        (self.next_u64() % len as u64) as usize
+    }
+
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        low + unit * (high - low)
+    }
+}
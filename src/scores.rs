@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::config::{config_file, write_atomically};
+use crate::error::Error;
+use crate::game::GameTimer;
+use crate::replay::Replay;
+use crate::{GRID_SIZES, GameSettings};
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+pub struct Entry {
+    pub time: f64,
+    pub seed: u64,
+    pub date: f64,
+    pub maze: String,
+}
+
+impl Entry {
+    pub fn date(&self) -> String {
+        // Civil date from days since the unix epoch (Howard Hinnant's algorithm).
+        let days = (self.date / 86400.).floor() as i64 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+#[derive(Default)]
+pub struct Submission {
+    pub new_best: bool,
+    pub rank: Option<usize>,
+}
+
+pub struct Scores {
+    best: HashMap<String, (f64, Replay)>,
+    leaderboard: HashMap<usize, Vec<Entry>>,
+}
+
+impl Scores {
+    pub fn new() -> Self {
+        Self {
+            best: HashMap::new(),
+            leaderboard: HashMap::new(),
+        }
+    }
+
+    pub fn load(settings: &GameSettings) -> Self {
+        let mut scores = Self::new();
+        let read = |name| config_file(name).and_then(|path| fs::read_to_string(path).ok());
+        if let Some(text) = read("scores.txt") {
+            text.lines()
+                .filter_map(parse_entry)
+                .for_each(|(labyrinth_size, entry)| {
+                    scores.insert(labyrinth_size, entry);
+                });
+        }
+        if let Some(text) = read("ghosts.txt") {
+            scores.best = text
+                .split("\n\n")
+                .filter_map(|block| parse_ghost(block, settings))
+                .collect();
+        }
+        scores
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let Some(path) = config_file("scores.txt") else {
+            return Ok(());
+        };
+        let text = self
+            .leaderboard
+            .iter()
+            .flat_map(|(labyrinth_size, entries)| {
+                entries.iter().map(|entry| {
+                    format!(
+                        "{} {} {} {} {}\n",
+                        GRID_SIZES[*labyrinth_size].0,
+                        entry.time,
+                        entry.seed,
+                        entry.date,
+                        entry.maze
+                    )
+                })
+            })
+            .collect::<String>();
+        write_atomically(&path, &text)?;
+
+        let Some(path) = config_file("ghosts.txt") else {
+            return Ok(());
+        };
+        let ghosts = self
+            .best
+            .iter()
+            .map(|(maze, (time, replay))| format_ghost(maze, *time, replay))
+            .collect::<Vec<_>>()
+            .join("\n");
+        write_atomically(&path, &ghosts)
+    }
+
+    pub fn best(&self, maze: &str) -> Option<&(f64, Replay)> {
+        self.best.get(maze)
+    }
+
+    pub fn leaderboard(&self, labyrinth_size: usize) -> &[Entry] {
+        self.leaderboard
+            .get(&labyrinth_size)
+            .map_or(&[], |entries| entries.as_slice())
+    }
+
+    pub fn insert(&mut self, labyrinth_size: usize, entry: Entry) -> Option<usize> {
+        let entries = self.leaderboard.entry(labyrinth_size).or_default();
+        let rank = entries.partition_point(|other| other.time <= entry.time);
+        if rank >= LEADERBOARD_SIZE {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+
+    pub fn submit(
+        &mut self,
+        maze: String,
+        settings: &GameSettings,
+        timer: &GameTimer,
+        replay: Replay,
+    ) -> Submission {
+        let Some(time) = timer.result.filter(|_| !timer.practice) else {
+            return Submission::default();
+        };
+        if !replay.verify(settings, time) {
+            return Submission::default();
+        }
+
+        let entry = Entry {
+            time,
+            seed: replay.seed,
+            date: macroquad::miniquad::date::now(),
+            maze: maze.clone(),
+        };
+        // Leaderboards only rank the preset sizes.
+        let rank = match settings.custom_grid_size {
+            Some(_) => None,
+            None => self.insert(settings.labyrinth_size, entry),
+        };
+
+        let new_best = match self.best.get(&maze) {
+            Some((best, _)) if *best <= time => false,
+            _ => {
+                self.best.insert(maze, (time, replay));
+                true
+            }
+        };
+        Submission { new_best, rank }
+    }
+}
+
+// One entry per line: size time seed date maze, malformed lines are skipped.
+fn parse_entry(line: &str) -> Option<(usize, Entry)> {
+    let mut parts = line.split_whitespace();
+    let size = parts.next()?;
+    let labyrinth_size = GRID_SIZES.iter().position(|(name, _)| *name == size)?;
+    let entry = Entry {
+        time: parts
+            .next()?
+            .parse()
+            .ok()
+            .filter(|time: &f64| time.is_finite())?,
+        seed: parts.next()?.parse().ok()?,
+        date: parts.next()?.parse().ok()?,
+        maze: parts.next()?.to_owned(),
+    };
+    Some((labyrinth_size, entry))
+}
+
+// One best run per block, blank line separated: the time, then the replay of the maze.
+fn format_ghost(maze: &str, time: f64, replay: &Replay) -> String {
+    format!("{}\n{}", time, replay.to_text(maze))
+}
+
+fn parse_ghost(block: &str, settings: &GameSettings) -> Option<(String, (f64, Replay))> {
+    let (time, replay) = block.split_once('\n')?;
+    let time = time.parse().ok().filter(|time: &f64| time.is_finite())?;
+    let maze = replay.lines().next()?.to_owned();
+    let (_, replay) = Replay::parse(replay, settings)?;
+    Some((maze, (time, replay)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometrie::Point;
+
+    #[test]
+    fn ghosts_round_trip() {
+        let settings = GameSettings::new(true, false, 1, 0.1, 10.0, 10.0);
+        let maze = settings.share_code(7);
+        let mut replay = Replay::new(7);
+        replay.record(0.0, Point::new(100.0, 100.0));
+        replay.record(0.5, Point::new(110.0, 100.0));
+        let text = [
+            format_ghost(&maze, 0.5, &replay),
+            format_ghost("8:1:0.2", 2.0, &replay),
+        ]
+        .join("\n");
+        let ghosts = text
+            .split("\n\n")
+            .filter_map(|block| parse_ghost(block, &settings))
+            .collect::<Vec<_>>();
+        assert_eq!(ghosts.len(), 2);
+        let (loaded, (time, ghost)) = &ghosts[0];
+        assert_eq!((loaded, *time), (&maze, 0.5));
+        assert_eq!(ghost.seed, 7);
+        assert_eq!(
+            ghost.positions().collect::<Vec<_>>(),
+            replay.positions().collect::<Vec<_>>()
+        );
+        assert_eq!(ghosts[1].1.1.seed, 8);
+        assert!(parse_ghost("NaN\n7:1:0.2\n", &settings).is_none());
+    }
+}
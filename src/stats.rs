@@ -0,0 +1,70 @@
+pub struct SessionStats {
+    pub completed: usize,
+    pub total_time: f64,
+    pub streak: usize,
+    completion_time: f64,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        Self {
+            completed: 0,
+            total_time: 0.0,
+            streak: 0,
+            completion_time: 0.0,
+        }
+    }
+
+    pub fn win(&mut self, time: f64) {
+        self.completed += 1;
+        self.streak += 1;
+        self.total_time += time;
+        self.completion_time += time;
+    }
+
+    pub fn quit(&mut self, time: f64) {
+        self.streak = 0;
+        self.total_time += time;
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        match self.completed {
+            0 => None,
+            n => Some(self.completion_time / n as f64),
+        }
+    }
+
+    pub fn lines(&self) -> [String; 3] {
+        [
+            format!("Wins {} Streak {}", self.completed, self.streak),
+            match self.average() {
+                Some(average) => format!("Avg {:.2}s", average),
+                None => "Avg --:--".to_owned(),
+            },
+            format!("Played {:.0}s", self.total_time),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_wins_and_quits() {
+        let mut stats = SessionStats::new();
+        assert_eq!(stats.average(), None);
+        stats.win(10.0);
+        stats.win(20.0);
+        assert_eq!((stats.completed, stats.streak), (2, 2));
+        stats.quit(5.0);
+        assert_eq!((stats.completed, stats.streak), (2, 0));
+        stats.win(30.0);
+        assert_eq!((stats.completed, stats.streak), (3, 1));
+        assert_eq!(stats.total_time, 65.0);
+        // Time spent on abandoned mazes does not count towards the average.
+        assert_eq!(stats.average(), Some(20.0));
+        assert_eq!(stats.lines()[0], "Wins 3 Streak 1");
+        assert_eq!(stats.lines()[1], "Avg 20.00s");
+    }
+}
@@ -0,0 +1,74 @@
+use macroquad::prelude::*;
+
+pub struct Theme {
+    pub rays: Color,
+    pub walls: Color,
+    pub player: Color,
+    pub target: Color,
+    pub hint: Color,
+    pub text: Color,
+    pub background: Color,
+}
+
+pub const THEMES: [(&str, Theme); 5] = [
+    (
+        "classic",
+        Theme {
+            rays: GREEN,
+            walls: BLUE,
+            player: WHITE,
+            target: RED,
+            hint: SKYBLUE,
+            text: WHITE,
+            background: BLACK,
+        },
+    ),
+    (
+        "high contrast",
+        Theme {
+            rays: YELLOW,
+            walls: WHITE,
+            player: Color::from_rgba(0, 255, 255, 255),
+            target: MAGENTA,
+            hint: LIME,
+            text: WHITE,
+            background: BLACK,
+        },
+    ),
+    (
+        "deuteranopia",
+        Theme {
+            rays: Color::from_rgba(86, 180, 233, 255),
+            walls: Color::from_rgba(0, 114, 178, 255),
+            player: WHITE,
+            target: Color::from_rgba(230, 159, 0, 255),
+            hint: Color::from_rgba(240, 228, 66, 255),
+            text: WHITE,
+            background: BLACK,
+        },
+    ),
+    (
+        "neon",
+        Theme {
+            rays: Color::from_rgba(0, 255, 255, 255),
+            walls: Color::from_rgba(255, 0, 200, 255),
+            player: WHITE,
+            target: Color::from_rgba(255, 240, 0, 255),
+            hint: Color::from_rgba(57, 255, 20, 255),
+            text: Color::from_rgba(0, 255, 255, 255),
+            background: Color::from_rgba(12, 0, 24, 255),
+        },
+    ),
+    (
+        "mono",
+        Theme {
+            rays: GRAY,
+            walls: LIGHTGRAY,
+            player: WHITE,
+            target: Color::from_rgba(150, 150, 150, 255),
+            hint: DARKGRAY,
+            text: WHITE,
+            background: BLACK,
+        },
+    ),
+];